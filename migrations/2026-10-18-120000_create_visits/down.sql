@@ -0,0 +1,23 @@
+CREATE TABLE newFriends (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    location TEXT NOT NULL,
+    freq_weeks INTEGER NOT NULL,
+    last_seen TEXT
+);
+
+INSERT INTO newFriends
+(id, name, location, freq_weeks, last_seen)
+SELECT id, name, location, freq_weeks, (
+    SELECT MAX(visits.date)
+    FROM visits
+    WHERE visits.friend_id = friends.id
+)
+FROM friends;
+
+DROP TABLE visits;
+
+DROP TABLE friends;
+
+ALTER TABLE newFriends
+RENAME TO friends;
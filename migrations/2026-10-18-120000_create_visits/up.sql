@@ -0,0 +1,29 @@
+CREATE TABLE visits (
+    id INTEGER PRIMARY KEY NOT NULL,
+    friend_id INTEGER NOT NULL REFERENCES friends(id) ON DELETE CASCADE,
+    date TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO visits
+(friend_id, date)
+SELECT id, last_seen
+FROM friends
+WHERE last_seen IS NOT NULL;
+
+CREATE TABLE newFriends (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    location TEXT NOT NULL,
+    freq_weeks INTEGER NOT NULL
+);
+
+INSERT INTO newFriends
+(id, name, location, freq_weeks)
+SELECT id, name, location, freq_weeks
+FROM friends;
+
+DROP TABLE friends;
+
+ALTER TABLE newFriends
+RENAME TO friends;
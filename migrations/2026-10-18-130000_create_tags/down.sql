@@ -0,0 +1,3 @@
+DROP TABLE friend_tags;
+
+DROP TABLE tags;
@@ -0,0 +1,21 @@
+CREATE TABLE newFriends (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    location TEXT NOT NULL,
+    freq_weeks INTEGER NOT NULL
+);
+
+INSERT INTO newFriends
+(id, name, location, freq_weeks)
+SELECT id, name, location, MAX(1, MIN(52, CASE freq_unit
+    WHEN 'day' THEN (freq_count + 6) / 7
+    WHEN 'week' THEN freq_count
+    WHEN 'month' THEN (freq_count * 30 + 6) / 7
+    ELSE freq_count * 52
+END))
+FROM friends;
+
+DROP TABLE friends;
+
+ALTER TABLE newFriends
+RENAME TO friends;
@@ -0,0 +1,17 @@
+CREATE TABLE newFriends (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    location TEXT NOT NULL,
+    freq_count INTEGER NOT NULL,
+    freq_unit TEXT NOT NULL
+);
+
+INSERT INTO newFriends
+(id, name, location, freq_count, freq_unit)
+SELECT id, name, location, freq_count, freq_unit
+FROM friends;
+
+DROP TABLE friends;
+
+ALTER TABLE newFriends
+RENAME TO friends;
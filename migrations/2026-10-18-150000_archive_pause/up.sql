@@ -0,0 +1,5 @@
+ALTER TABLE friends
+ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0;
+
+ALTER TABLE friends
+ADD COLUMN paused_until TEXT;
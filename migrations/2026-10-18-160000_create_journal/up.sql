@@ -0,0 +1,7 @@
+CREATE TABLE journal (
+    id INTEGER PRIMARY KEY NOT NULL,
+    command TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    undone BOOLEAN NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
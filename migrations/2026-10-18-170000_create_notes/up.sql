@@ -0,0 +1,7 @@
+CREATE TABLE notes (
+    id INTEGER PRIMARY KEY NOT NULL,
+    friend_id INTEGER NOT NULL REFERENCES friends(id) ON DELETE CASCADE,
+    date TEXT NOT NULL,
+    text TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
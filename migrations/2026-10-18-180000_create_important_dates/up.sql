@@ -0,0 +1,9 @@
+CREATE TABLE important_dates (
+    id INTEGER PRIMARY KEY NOT NULL,
+    friend_id INTEGER NOT NULL REFERENCES friends(id) ON DELETE CASCADE,
+    label TEXT NOT NULL,
+    year INTEGER,
+    month INTEGER NOT NULL,
+    day INTEGER NOT NULL,
+    UNIQUE (friend_id, label)
+);
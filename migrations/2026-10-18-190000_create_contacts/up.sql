@@ -0,0 +1,7 @@
+CREATE TABLE contacts (
+    id INTEGER PRIMARY KEY NOT NULL,
+    friend_id INTEGER NOT NULL REFERENCES friends(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    label TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL
+);
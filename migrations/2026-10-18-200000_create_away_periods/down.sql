@@ -0,0 +1 @@
+DROP TABLE away_periods;
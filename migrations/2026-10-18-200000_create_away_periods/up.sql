@@ -0,0 +1,6 @@
+CREATE TABLE away_periods (
+    id INTEGER PRIMARY KEY NOT NULL,
+    location TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL
+);
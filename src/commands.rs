@@ -1,101 +1,962 @@
+use crate::config::{Config, ConfigKey};
+use crate::contacts::{self, ContactKind};
 use crate::db::{self, SqliteConnection};
+use crate::frequency::Frequency;
+use crate::import::{self, ImportStatus};
+use crate::important_dates::{self, DateRecord};
 use crate::models::*;
-use crate::upcoming::UpcomingFriends;
-use crate::{dates, ListOrderBy};
+use crate::output::Output;
+use crate::profile::{self, ProfileRecord};
+use crate::snapshot::{self, MergeStrategy};
+use crate::stats::{self, Stats};
+use crate::upcoming::{UpcomingFriends, UpcomingOptions};
+use crate::{dates, ics, journal, names, notes, trip, tui, vcard, ListOrderBy};
 use anyhow::{Context, Result};
-use prettytable::{format, Table};
-
-const DEFAULT_FREQ_WEEKS: i32 = 10;
+use chrono::{Duration, Utc};
+use diesel::Connection;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 pub fn list_friends(
     order_by: ListOrderBy,
     number: Option<i64>,
+    tag: Option<String>,
+    all: bool,
+    out: &Output,
     conn: &SqliteConnection,
 ) -> Result<()> {
-    let all_friends = db::load_all_friends_ordered(order_by.into(), number, conn)
-        .context("Failed to load friends")?;
-    if all_friends.is_empty() {
-        return Err(anyhow::anyhow!(
-            "No friends yet. Add some with the `add` command."
-        ));
+    let tag = tag.as_deref().map(names::normalize_tag).transpose()?;
+    let today = dates::local_today();
+    let active_on = Some(today).filter(|_| !all);
+    let all_friends =
+        db::load_all_friends_ordered(order_by, number, tag.as_deref(), active_on, conn)
+            .context("Failed to load friends")?;
+    if all_friends.is_empty() && !out.is_structured() {
+        return Err(no_friends_listed(tag.as_deref(), conn)?);
     }
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-    table.set_titles(Friend::get_table_titles(false));
+    let records = all_friends
+        .into_iter()
+        .map(|friend| FriendRecord::new(friend, today, out.due_display()))
+        .collect::<Result<Vec<_>>>()?;
+    out.many(&records)
+}
 
-    for friend in all_friends {
-        table.add_row(friend.get_table_row());
+/// Why `list` found nobody: no friends at all, none with the tag, or only inactive ones.
+fn no_friends_listed(tag: Option<&str>, conn: &SqliteConnection) -> Result<anyhow::Error> {
+    let all_names = db::load_all_names(conn).context("Failed to load friends")?;
+    if all_names.is_empty() {
+        return Ok(anyhow::anyhow!(
+            "No friends yet. Add some with the `add` command."
+        ));
     }
+    let error = match tag {
+        Some(tag) => {
+            let tagged =
+                db::load_all_friends(Some(tag), None, conn).context("Failed to load friends")?;
+            match tagged.is_empty() {
+                true => anyhow::anyhow!("No friends are tagged {}", tag),
+                false => anyhow::anyhow!("No active friends are tagged {}, use --all", tag),
+            }
+        }
+        None => anyhow::anyhow!("No active friends, use --all"),
+    };
+    Ok(error)
+}
 
-    table.printstd();
-    Ok(())
+pub fn show_friend(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    out.one(&FriendRecord::new(
+        friend,
+        dates::local_today(),
+        out.due_display(),
+    )?)
 }
 
-pub fn show_friend(name: String, conn: &SqliteConnection) -> Result<()> {
+/// The friend, with their most recent notes underneath.
+pub fn show_friend_details(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
     let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
-    println!("{}", friend);
+    let recent =
+        db::load_notes(&friend, Some(notes::RECENT_NOTES), conn).context("Failed to load notes")?;
+    out.one(&FriendRecord::new(
+        friend,
+        dates::local_today(),
+        out.due_display(),
+    )?)?;
+    if !out.is_structured() {
+        for note in recent {
+            println!("  {}", note);
+        }
+    }
     Ok(())
 }
 
 pub fn add_friend(
     name: String,
     location: String,
-    freq_weeks: Option<i32>,
+    frequency: Frequency,
+    out: &Output,
     conn: &SqliteConnection,
 ) -> Result<()> {
+    let all_names = db::load_all_names(conn).context("Failed to load friends")?;
+    names::check_new(&name, &all_names, None)?;
     let new_friend = NewFriend {
         name: name.clone(),
         location: location.clone(),
-        freq_weeks: freq_weeks.unwrap_or(DEFAULT_FREQ_WEEKS),
+        frequency,
     };
 
     db::insert_friend(new_friend, conn).context("Failed to add friend")?;
-    show_friend(name, conn)
+    show_friend(name, out, conn)
 }
 
-pub fn remove_friend(name: String, conn: &SqliteConnection) -> Result<()> {
-    show_friend(name.clone(), conn)?;
-    db::delete_friend(&name, conn).context("Failed to remove friend")?;
-    Ok(())
+pub fn remove_friend(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend_exact(&name, conn).context("Failed to load friend")?;
+    db::delete_friend(&friend, conn).context("Failed to remove friend")?;
+    out.one(&FriendRecord::new(
+        friend,
+        dates::local_today(),
+        out.due_display(),
+    )?)
+}
+
+pub fn archive_friend(
+    name: String,
+    archive: bool,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    if friend.archived == archive {
+        let state = if archive { "already" } else { "not" };
+        return Err(anyhow::anyhow!("{} is {} archived", friend.name, state));
+    }
+    db::update_archived(&friend, archive, conn).context("Failed to archive friend")?;
+    show_friend(friend.name, out, conn)
+}
+
+pub fn pause_friend(
+    name: String,
+    until: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let today = dates::local_today();
+    let until = dates::parse_future_date(&until, today)?;
+    if until < today {
+        return Err(anyhow::anyhow!(
+            "Can't pause until {}, which has already passed",
+            until
+        ));
+    }
+
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    db::update_paused_until(&friend, until.to_string(), conn).context("Failed to pause friend")?;
+    show_friend(friend.name, out, conn)
+}
+
+pub fn set_name(
+    curr_name: String,
+    new_name: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&curr_name, conn).context("Failed to load friend")?;
+    let all_names = db::load_all_names(conn).context("Failed to load friends")?;
+    names::check_new(&new_name, &all_names, Some(&friend.name))?;
+    db::update_name(&friend, &new_name, conn).context("Failed to set name")?;
+    show_friend(new_name, out, conn)
+}
+
+pub fn set_location(
+    name: String,
+    location: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    db::update_location(&friend, location, conn).context("Failed to set location")?;
+    show_friend(friend.name, out, conn)
+}
+
+pub fn set_frequency(
+    name: String,
+    frequency: Frequency,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    db::update_frequency(&friend, frequency, conn).context("Failed to set frequency")?;
+    show_friend(friend.name, out, conn)
+}
+
+pub fn record_seen(
+    names: Vec<String>,
+    date: String,
+    tag: Option<String>,
+    backfill: bool,
+    note: Option<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let new_date = dates::parse_date_arg(&date)?;
+    let single = names.len() == 1 && tag.is_none();
+    let friends = load_named_or_tagged(&names, tag, conn)?;
+
+    let seen_on = new_date.to_string();
+    conn.transaction::<_, anyhow::Error, _>(|| {
+        for friend in &friends {
+            dates::check_new_seen(new_date, friend.last_seen.clone(), backfill)
+                .with_context(|| format!("Failed to record seeing {}", friend.name))?;
+
+            if db::visit_exists(friend, &seen_on, conn).context("Failed to load visits")? {
+                eprintln!(
+                    "Warning: already recorded seeing {} on {}",
+                    friend.name, seen_on
+                );
+            } else {
+                db::insert_visit(friend, seen_on.clone(), conn).context("Failed to record seen")?;
+            }
+            if let Some(note) = &note {
+                db::insert_note(friend, seen_on.clone(), note.clone(), conn)
+                    .context("Failed to save note")?;
+            }
+        }
+        Ok(())
+    })?;
+
+    if single {
+        return show_friend_details(friends[0].name.clone(), out, conn);
+    }
+    let today = dates::local_today();
+    let records = friends
+        .into_iter()
+        .map(|friend| {
+            let friend = db::load_friend(&friend.name, conn).context("Failed to load friend")?;
+            FriendRecord::new(friend, today, out.due_display())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    out.many(&records)
+}
+
+/// The hint for a note about seeing friends written in the editor, failing before the
+/// editor opens if the date or any friend is wrong.
+pub fn seen_note_hint(
+    names: &[String],
+    date: &str,
+    tag: Option<String>,
+    conn: &SqliteConnection,
+) -> Result<String> {
+    let seen_on = dates::parse_date_arg(date)?;
+    let friends = load_named_or_tagged(names, tag, conn)?;
+    let names: Vec<&str> = friends.iter().map(|f| f.name.as_str()).collect();
+    Ok(format!(
+        "Note about seeing {} on {}",
+        names.join(", "),
+        seen_on
+    ))
+}
+
+/// The hint for a note about a friend written in the editor, failing before the editor
+/// opens if the date or friend is wrong.
+pub fn note_hint(name: &str, date: &str, conn: &SqliteConnection) -> Result<String> {
+    let note_date = dates::parse_date_arg(date)?;
+    let friend = db::load_friend(name, conn).context("Failed to load friend")?;
+    Ok(format!("Note about {} on {}", friend.name, note_date))
+}
+
+/// Every friend named and every active friend with the tag, once each, failing if any
+/// name doesn't match a friend.
+fn load_named_or_tagged(
+    names: &[String],
+    tag: Option<String>,
+    conn: &SqliteConnection,
+) -> Result<Vec<Friend>> {
+    let mut friends = vec![];
+    for name in names {
+        friends.push(db::load_friend(name, conn).context("Failed to load friend")?);
+    }
+    if let Some(tag) = tag {
+        let tag = names::normalize_tag(&tag)?;
+        let tagged = db::load_all_friends(Some(&tag), Some(dates::local_today()), conn)
+            .context("Failed to load friends")?;
+        if tagged.is_empty() {
+            return Err(anyhow::anyhow!("No friends are tagged {}", tag));
+        }
+        friends.extend(tagged);
+    }
+    if friends.is_empty() {
+        return Err(anyhow::anyhow!("Name a friend to record, or pass --tag"));
+    }
+
+    let mut seen_ids = HashSet::new();
+    friends.retain(|friend| seen_ids.insert(friend.id));
+    Ok(friends)
+}
+
+pub fn show_history(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let visits = db::load_visits(&friend, conn).context("Failed to load visits")?;
+    if !out.is_structured() {
+        println!("{}", friend);
+        if visits.is_empty() {
+            return Ok(());
+        }
+    }
+    out.many(&visits)
+}
+
+pub fn add_note(
+    name: String,
+    text: Option<String>,
+    date: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let note_date = dates::parse_date_arg(&date)?;
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let text = text
+        .as_deref()
+        .and_then(notes::clean)
+        .ok_or_else(|| anyhow::anyhow!("The note is empty, so it wasn't saved"))?;
+
+    db::insert_note(&friend, note_date.to_string(), text, conn).context("Failed to save note")?;
+    show_friend_details(friend.name, out, conn)
+}
+
+pub fn show_timeline(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let visits = db::load_visits(&friend, conn).context("Failed to load visits")?;
+    let all_notes = db::load_notes(&friend, None, conn).context("Failed to load notes")?;
+    let entries = notes::timeline(&visits, &all_notes);
+    if !out.is_structured() {
+        println!("{}", friend);
+        if entries.is_empty() {
+            return Ok(());
+        }
+    }
+    out.many(&entries)
 }
 
-pub fn set_name(curr_name: String, new_name: String, conn: &SqliteConnection) -> Result<()> {
-    db::update_name(&curr_name, &new_name, conn).context("Failed to set name")?;
-    show_friend(new_name, conn)
+/// Add an important date, or change it when the friend already has one with that label.
+pub fn set_date(
+    name: String,
+    label: String,
+    date: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let label = label.trim().to_string();
+    if label.is_empty() {
+        return Err(anyhow::anyhow!("Give the date a label, like Anniversary"));
+    }
+    let (year, month, day) = dates::parse_yearly_date(&date)?;
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let existing = db::load_dates(&friend, conn).context("Failed to load dates")?;
+
+    let new_date = NewImportantDate {
+        friend_id: friend.id,
+        label,
+        year,
+        month: month as i32,
+        day: day as i32,
+    };
+    match existing
+        .iter()
+        .find(|d| names::normalize(&d.label) == names::normalize(&new_date.label))
+    {
+        Some(current) => db::update_date(current.id, &new_date, conn),
+        None => db::insert_date(&new_date, conn),
+    }
+    .context("Failed to save date")?;
+    list_dates(friend.name, out, conn)
+}
+
+pub fn set_birthday(
+    name: String,
+    date: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    set_date(name, important_dates::BIRTHDAY.to_string(), date, out, conn)
+}
+
+pub fn remove_date(
+    name: String,
+    label: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let existing = db::load_dates(&friend, conn).context("Failed to load dates")?;
+    let date = existing
+        .iter()
+        .find(|d| names::normalize(&d.label) == names::normalize(&label))
+        .ok_or_else(|| anyhow::anyhow!("{} has no date labelled '{}'", friend.name, label))?;
+    db::delete_date(date.id, conn).context("Failed to remove date")?;
+    list_dates(friend.name, out, conn)
 }
 
-pub fn set_location(name: String, location: String, conn: &SqliteConnection) -> Result<()> {
-    db::update_location(&name, location, conn).context("Failed to set location")?;
-    show_friend(name, conn)
+pub fn list_dates(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let today = dates::local_today();
+    let records = db::load_dates(&friend, conn)
+        .context("Failed to load dates")?
+        .iter()
+        .map(|date| DateRecord::new(date, today, out.due_display()))
+        .collect::<Result<Vec<_>>>()?;
+    if !out.is_structured() {
+        println!("{}", friend);
+        if records.is_empty() {
+            return Ok(());
+        }
+    }
+    out.many(&records)
 }
 
-pub fn set_frequency(name: String, freq_weeks: i32, conn: &SqliteConnection) -> Result<()> {
-    dates::check_frequency(freq_weeks)?;
+pub fn show_contacts(name: String, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let friend_contacts = db::load_contacts(&friend, conn).context("Failed to load contacts")?;
+    if !out.is_structured() {
+        println!("{}", friend);
+        if friend_contacts.is_empty() {
+            return Ok(());
+        }
+    }
+    out.many(&friend_contacts)
+}
 
-    db::update_freq_weeks(&name, freq_weeks, conn).context("Failed to set frequency")?;
-    show_friend(name, conn)
+pub fn add_contact(
+    name: String,
+    kind: ContactKind,
+    value: String,
+    label: Option<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let new_contact = NewContact {
+        friend_id: friend.id,
+        kind,
+        label: label.unwrap_or_default().trim().to_string(),
+        value: contacts::check_value(kind, &value)?,
+    };
+    db::insert_contact(&new_contact, conn).context("Failed to add contact")?;
+    show_contacts(friend.name, out, conn)
 }
 
-pub fn record_seen(name: String, date: String, conn: &SqliteConnection) -> Result<()> {
-    let new_date = dates::parse_date(&date)?;
+/// Remove the contact with this value, or with this label when only one has it.
+pub fn remove_contact(
+    name: String,
+    contact: String,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    let friend_contacts = db::load_contacts(&friend, conn).context("Failed to load contacts")?;
+    let matching: Vec<&Contact> = match friend_contacts.iter().find(|c| c.value == contact.trim()) {
+        Some(found) => vec![found],
+        None => friend_contacts
+            .iter()
+            .filter(|c| names::normalize(&c.label) == names::normalize(&contact))
+            .collect(),
+    };
+    let found = match matching.as_slice() {
+        [found] => found,
+        [] => {
+            return Err(anyhow::anyhow!(
+                "{} has no contact '{}'",
+                friend.name,
+                contact
+            ))
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "{} has several contacts labelled '{}', give the value to remove instead",
+                friend.name,
+                contact
+            ))
+        }
+    };
+    db::delete_contact(found.id, conn).context("Failed to remove contact")?;
+    show_contacts(friend.name, out, conn)
+}
 
+pub fn tag_friend(
+    name: String,
+    tags: Vec<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
     let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
-    dates::check_new_seen(new_date, friend.last_seen)?;
+    for tag in tags {
+        let tag = names::normalize_tag(&tag)?;
+        db::insert_tag(friend.id, &tag, conn).context("Failed to tag friend")?;
+    }
+    show_friend(friend.name, out, conn)
+}
 
-    db::update_last_seen(&name, new_date.to_string(), conn).context("Failed to record seen")?;
-    show_friend(name, conn)
+pub fn untag_friend(
+    name: String,
+    tags: Vec<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let friend = db::load_friend(&name, conn).context("Failed to load friend")?;
+    for tag in tags {
+        let tag = names::normalize_tag(&tag)?;
+        if !friend.tags.contains(&tag) {
+            return Err(anyhow::anyhow!("{} is not tagged {}", friend.name, tag));
+        }
+        db::delete_tag(&friend, &tag, conn).context("Failed to untag friend")?;
+    }
+    show_friend(friend.name, out, conn)
 }
 
-pub fn list_upcoming(conn: &SqliteConnection) -> Result<()> {
-    let results = db::load_all_friends(conn).context("Failed to load friends")?;
+pub fn list_upcoming(
+    options: UpcomingOptions,
+    tag: Option<String>,
+    all: bool,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let tag = tag.as_deref().map(names::normalize_tag).transpose()?;
     let today = dates::local_today();
-    let mut upcoming_friends = UpcomingFriends::new();
+    let active_on = Some(today).filter(|_| !all);
+    let results =
+        db::load_all_friends(tag.as_deref(), active_on, conn).context("Failed to load friends")?;
+    let important_dates = db::load_all_dates(conn).context("Failed to load dates")?;
+    if let Some(away) = options.away.as_ref().filter(|_| !out.is_structured()) {
+        println!(
+            "Away in {} until {}, so only friends there are listed. Use --ignore-travel for everyone.",
+            away.location,
+            out.due_display().date(away.until)
+        );
+    }
+    let mut upcoming_friends = UpcomingFriends::new(options);
 
     for friend in results {
-        upcoming_friends.push(friend.clone(), today)?;
+        for date in important_dates.iter().filter(|d| d.friend_id == friend.id) {
+            upcoming_friends.push_date(&friend, date.clone(), today);
+        }
+        upcoming_friends.push(friend, today)?;
     }
 
-    upcoming_friends.print();
+    out.many(&upcoming_friends.into_rows(today, out.due_display())?)
+}
+
+pub fn plan_trip(
+    location: String,
+    from: Option<String>,
+    to: Option<String>,
+    per_day: usize,
+    aliases: &BTreeMap<String, String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let today = dates::local_today();
+    let from = match from {
+        Some(from) => dates::parse_future_date(&from, today)?,
+        None => today,
+    };
+    let to = match to {
+        Some(to) => dates::parse_future_date(&to, from)?,
+        None => from,
+    };
+    if to < from {
+        return Err(anyhow::anyhow!(
+            "The trip can't end on {} before it starts on {}",
+            to,
+            from
+        ));
+    }
+    if (to - from).num_days() >= trip::MAX_TRIP_DAYS {
+        return Err(anyhow::anyhow!(
+            "Can't plan a trip longer than {} days",
+            trip::MAX_TRIP_DAYS
+        ));
+    }
+    if per_day == 0 {
+        return Err(anyhow::anyhow!("Plan to see at least one friend a day"));
+    }
+
+    let place_names = trip::place_names(&location, aliases);
+    let nearby: Vec<Friend> = db::load_all_friends(None, Some(from), conn)
+        .context("Failed to load friends")?
+        .into_iter()
+        .filter(|friend| trip::in_place(&friend.location, &place_names))
+        .collect();
+    if nearby.is_empty() && !out.is_structured() {
+        return Err(anyhow::anyhow!("No friends found in {}", location));
+    }
+
+    out.many(&trip::plan(nearby, from, to, per_day, out.due_display())?)
+}
+
+pub fn show_stats(
+    since: Option<String>,
+    tag: Option<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let tag = tag.as_deref().map(names::normalize_tag).transpose()?;
+    let today = dates::local_today();
+    let since = match since {
+        Some(since) => dates::parse_natural_date(&since, today)?,
+        None => today - Duration::days(stats::DEFAULT_WINDOW_DAYS),
+    };
+    if since > today {
+        return Err(anyhow::anyhow!(
+            "Can't count visits since {}, which is still to come",
+            since
+        ));
+    }
+
+    let all_friends = db::load_all_friends(tag.as_deref(), Some(today), conn)
+        .context("Failed to load friends")?;
+    let visits = db::load_all_visits(conn).context("Failed to load visits")?;
+    out.one(&Stats::new(&all_friends, &visits, since, today)?)
+}
+
+/// Where you are today if away, unless you've gone home.
+pub fn current_away(
+    aliases: &BTreeMap<String, String>,
+    conn: &SqliteConnection,
+) -> Result<Option<trip::Away>> {
+    let today = dates::local_today();
+    db::load_away_on(today, conn)
+        .context("Failed to load times away")?
+        .map(|period| trip::Away::new(&period, aliases, today))
+        .transpose()
+}
+
+pub fn add_away(
+    location: String,
+    from: Option<String>,
+    to: Option<String>,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let today = dates::local_today();
+    let from = match from {
+        Some(from) => dates::parse_future_date(&from, today)?,
+        None => today,
+    };
+    let to = match to {
+        Some(to) => dates::parse_future_date(&to, from)?,
+        None => return Err(anyhow::anyhow!("Say when you'll be back with --to")),
+    };
+    if to < from {
+        return Err(anyhow::anyhow!(
+            "Can't be away until {}, before leaving on {}",
+            to,
+            from
+        ));
+    }
+    if to < today {
+        return Err(anyhow::anyhow!(
+            "Can't be away until {}, which has already passed",
+            to
+        ));
+    }
+    let location = location.trim().to_string();
+    if location.is_empty() {
+        return Err(anyhow::anyhow!("Say where you'll be"));
+    }
+
+    let periods = db::load_away_periods(conn).context("Failed to load times away")?;
+    if let Some(overlapping) = periods
+        .iter()
+        .find(|p| p.start_date <= to.to_string() && from.to_string() <= p.end_date)
+    {
+        return Err(anyhow::anyhow!(
+            "That overlaps with being away in {} from {} to {}, remove it with `away --remove {}`",
+            overlapping.location,
+            overlapping.start_date,
+            overlapping.end_date,
+            overlapping.id
+        ));
+    }
+
+    let new_period = NewAwayPeriod {
+        location,
+        start_date: from.to_string(),
+        end_date: to.to_string(),
+    };
+    db::insert_away_periods(&[new_period], conn).context("Failed to add time away")?;
+    list_away(out, conn)
+}
+
+pub fn remove_away(period_id: i32, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    match db::delete_away_period(period_id, conn).context("Failed to remove time away")? {
+        0 => Err(anyhow::anyhow!("No time away with ID {}", period_id)),
+        _ => list_away(out, conn),
+    }
+}
+
+pub fn list_away(out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let periods = db::load_away_periods(conn).context("Failed to load times away")?;
+    if periods.is_empty() && !out.is_structured() {
+        println!("No times away");
+        return Ok(());
+    }
+    out.many(&periods)
+}
+
+pub fn export_ics(
+    kind: ics::EntryKind,
+    file: Option<PathBuf>,
+    tag: Option<String>,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let tag = tag.as_deref().map(names::normalize_tag).transpose()?;
+    let today = dates::local_today();
+    let all_friends = db::load_all_friends(tag.as_deref(), Some(today), conn)
+        .context("Failed to load friends")?;
+    let calendar = ics::calendar(&all_friends, kind, today, Utc::now())?;
+
+    match file {
+        Some(file) => {
+            fs::write(&file, calendar).context("Failed to write calendar")?;
+            println!(
+                "Exported {} friends to {}",
+                all_friends.len(),
+                file.display()
+            );
+        }
+        None => print!("{}", calendar),
+    }
     Ok(())
 }
+
+pub fn export_vcard(
+    name: Option<String>,
+    file: Option<PathBuf>,
+    tag: Option<String>,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let tag = tag.as_deref().map(names::normalize_tag).transpose()?;
+    let all_friends = match name {
+        Some(name) => vec![db::load_friend(&name, conn).context("Failed to load friend")?],
+        None => {
+            db::load_all_friends(tag.as_deref(), None, conn).context("Failed to load friends")?
+        }
+    };
+
+    let mut cards = String::new();
+    for friend in &all_friends {
+        let friend_contacts = db::load_contacts(friend, conn).context("Failed to load contacts")?;
+        let friend_dates = db::load_dates(friend, conn).context("Failed to load dates")?;
+        let birthday = friend_dates
+            .iter()
+            .find(|d| names::normalize(&d.label) == names::normalize(important_dates::BIRTHDAY));
+        cards.push_str(&vcard::card(friend, &friend_contacts, birthday));
+    }
+
+    match file {
+        Some(file) => {
+            fs::write(&file, cards).context("Failed to write vCard file")?;
+            println!(
+                "Exported {} friends to {}",
+                all_friends.len(),
+                file.display()
+            );
+        }
+        None => print!("{}", cards),
+    }
+    Ok(())
+}
+
+pub fn import_vcard(
+    file: PathBuf,
+    frequency: Frequency,
+    dry_run: bool,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let text = fs::read_to_string(&file).context("Failed to read vCard file")?;
+    let cards = vcard::parse(&text).context("Failed to parse vCard file")?;
+    let candidates = cards
+        .iter()
+        .map(|card| (card.formatted_name(), card.locality().unwrap_or_default()))
+        .collect();
+
+    let existing_names = db::load_all_names(conn).context("Failed to load friends")?;
+    let (new_friends, mut records) = import::plan(candidates, frequency, &existing_names);
+
+    if !dry_run {
+        db::insert_friends(&new_friends, conn).context("Failed to import friends")?;
+        for record in records.iter_mut() {
+            if record.status == ImportStatus::WouldAdd {
+                record.status = ImportStatus::Added;
+            }
+        }
+    }
+    out.many(&records)
+}
+
+pub fn export_snapshot(file: Option<PathBuf>, conn: &SqliteConnection) -> Result<()> {
+    let snapshot = snapshot::take(conn).context("Failed to load database")?;
+    let json = serde_json::to_string_pretty(&snapshot)?;
+
+    match file {
+        Some(file) => {
+            fs::write(&file, json).context("Failed to write snapshot")?;
+            println!(
+                "Exported {} friends to {}",
+                snapshot.friends.len(),
+                file.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+pub fn import_snapshot(
+    file: PathBuf,
+    merge: MergeStrategy,
+    dry_run: bool,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let json = fs::read_to_string(&file).context("Failed to read snapshot")?;
+    let snapshot = serde_json::from_str(&json).context("Failed to parse snapshot")?;
+
+    let records =
+        snapshot::restore(&snapshot, merge, dry_run, conn).context("Failed to import snapshot")?;
+    out.many(&records)
+}
+
+pub fn import_csv(
+    file: PathBuf,
+    mappings: Vec<String>,
+    frequency: Frequency,
+    merge: MergeStrategy,
+    dry_run: bool,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let mapping = snapshot::parse_column_mapping(&mappings)?;
+    let text = fs::read_to_string(&file).context("Failed to read CSV file")?;
+    let snapshot = snapshot::from_csv(&text, &mapping, frequency, dates::local_today())
+        .context("Failed to parse CSV file")?;
+
+    let records =
+        snapshot::restore(&snapshot, merge, dry_run, conn).context("Failed to import friends")?;
+    out.many(&records)
+}
+
+pub fn run_tui(options: UpcomingOptions, out: &Output, conn: &SqliteConnection) -> Result<()> {
+    tui::run(options, out.due_display().clone(), conn)
+}
+
+pub fn undo(out: &Output, conn: &SqliteConnection) -> Result<()> {
+    out.one(&journal::undo(conn).context("Failed to undo")?)
+}
+
+pub fn redo(out: &Output, conn: &SqliteConnection) -> Result<()> {
+    out.one(&journal::redo(conn).context("Failed to redo")?)
+}
+
+pub fn list_journal(out: &Output, conn: &SqliteConnection) -> Result<()> {
+    let records = journal::list(conn).context("Failed to load journal")?;
+    if records.is_empty() && !out.is_structured() {
+        return Err(anyhow::anyhow!("Nothing to undo"));
+    }
+    out.many(&records)
+}
+
+pub fn show_config(config_path: &Path, database_path: PathBuf, config: &Config) -> Result<()> {
+    let mut config = config.clone();
+    config.database = Some(database_path);
+    println!("# {}", config_path.display());
+    print!("{}", config.to_toml()?);
+    Ok(())
+}
+
+pub fn set_config(
+    config_path: &Path,
+    config: &Config,
+    key: ConfigKey,
+    value: String,
+) -> Result<()> {
+    let mut config = config.clone();
+    config.set(key, &value)?;
+    config.save(config_path)?;
+    println!("Set {} to {} in {}", key, value, config_path.display());
+    Ok(())
+}
+
+pub fn list_profiles(active: &str, config: &Config, out: &Output) -> Result<()> {
+    let records = profile::list()?
+        .into_iter()
+        .map(|name| {
+            Ok(ProfileRecord {
+                database: profile::database_path(&name, config)?,
+                active: name == active,
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    out.many(&records)
+}
+
+pub fn create_profile(name: String, active: &str, config: &Config, out: &Output) -> Result<()> {
+    if profile::exists(&name, config)? {
+        return Err(anyhow::anyhow!("Profile {} already exists", name));
+    }
+    let database = profile::database_path(&name, config)?;
+    db::connect(&database).context("Failed to create profile database")?;
+    out.one(&ProfileRecord {
+        active: name == active,
+        name,
+        database,
+    })
+}
+
+pub fn switch_profile(
+    name: String,
+    config_path: &Path,
+    config: &Config,
+    out: &Output,
+) -> Result<()> {
+    if !profile::exists(&name, config)? {
+        return Err(anyhow::anyhow!(
+            "No profile named {}, create it with `profile create {}`",
+            name,
+            name
+        ));
+    }
+    let mut config = config.clone();
+    config.profile = Some(name.clone()).filter(|name| name != profile::DEFAULT_PROFILE);
+    config.save(config_path)?;
+    out.one(&ProfileRecord {
+        database: profile::database_path(&name, &config)?,
+        active: true,
+        name,
+    })
+}
+
+pub fn delete_profile(name: String, active: &str, config: &Config, out: &Output) -> Result<()> {
+    if name == profile::DEFAULT_PROFILE {
+        return Err(anyhow::anyhow!("The default profile can't be deleted"));
+    }
+    if name == active {
+        return Err(anyhow::anyhow!(
+            "Profile {} is in use, switch to another one before deleting it",
+            name
+        ));
+    }
+    if !profile::exists(&name, config)? {
+        return Err(anyhow::anyhow!("No profile named {}", name));
+    }
+    let database = profile::database_path(&name, config)?;
+    fs::remove_file(&database).context("Failed to delete profile database")?;
+    out.one(&ProfileRecord {
+        name,
+        database,
+        active: false,
+    })
+}
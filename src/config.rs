@@ -0,0 +1,272 @@
+use crate::frequency::Frequency;
+use crate::output::OutputFormat;
+use crate::profile;
+use crate::upcoming::{DueDisplay, DueStyle, DEFAULT_DUE_DATE_FORMAT, UP_SOON_CUTOFF_DAYS};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use strum::{Display, EnumString, EnumVariantNames, IntoStaticStr};
+use toml::value::Table;
+
+pub const DEFAULT_FREQUENCY: Frequency = Frequency::weeks(10);
+const APP_DIR: &str = "friendgrow";
+const CONFIG_FILE: &str = "config.toml";
+const DB_FILE: &str = "friendgrow.db";
+
+/// Settings from the config file, with defaults for anything it leaves out.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: Option<PathBuf>,
+    /// The profile switched to, whose database is used instead
+    pub profile: Option<String>,
+    #[serde(alias = "default_freq_weeks")]
+    pub default_frequency: Frequency,
+    pub upcoming_days: u16,
+    pub date_format: String,
+    pub due_style: DueStyle,
+    pub format: OutputFormat,
+    /// Other names for places, like `NYC = "New York"`, for finding friends by location
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub location_aliases: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database: None,
+            profile: None,
+            default_frequency: DEFAULT_FREQUENCY,
+            upcoming_days: UP_SOON_CUTOFF_DAYS,
+            date_format: DEFAULT_DUE_DATE_FORMAT.to_string(),
+            due_style: DueStyle::Absolute,
+            format: OutputFormat::Table,
+            location_aliases: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Display, EnumString, EnumVariantNames, IntoStaticStr)]
+pub enum ConfigKey {
+    #[strum(serialize = "database")]
+    Database,
+    #[strum(serialize = "default_frequency")]
+    DefaultFrequency,
+    #[strum(serialize = "upcoming_days")]
+    UpcomingDays,
+    #[strum(serialize = "date_format")]
+    DateFormat,
+    #[strum(serialize = "due_style")]
+    DueStyle,
+    #[strum(serialize = "format")]
+    Format,
+}
+
+impl Config {
+    /// The config file to use, `$XDG_CONFIG_HOME/friendgrow/config.toml` unless given.
+    pub fn path(custom: Option<PathBuf>) -> Result<PathBuf> {
+        match custom {
+            Some(path) => Ok(path),
+            None => Ok(xdg_dir("XDG_CONFIG_HOME", ".config")?
+                .join(APP_DIR)
+                .join(CONFIG_FILE)),
+        }
+    }
+
+    /// Load the config file, which doesn't need to exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).context("Failed to read config file")?;
+        let config: Self = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Load the settings in the config file that are valid, warning about the rest, so
+    /// `config` can show and fix a file that doesn't load. The file must still be TOML.
+    pub fn load_lenient(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path).context("Failed to read config file")?;
+        let table: Table = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+        let mut valid = Table::new();
+        for (key, value) in table {
+            let mut with_key = valid.clone();
+            with_key.insert(key.clone(), value);
+            match Self::from_table(with_key.clone()) {
+                Ok(_) => valid = with_key,
+                Err(e) => eprintln!("Warning: ignoring {} in {}: {:#}", key, path.display(), e),
+            }
+        }
+        Self::from_table(valid)
+    }
+
+    fn from_table(table: Table) -> Result<Self> {
+        let config: Self = toml::Value::Table(table).try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("Failed to create config directory")?;
+        }
+        fs::write(path, self.to_toml()?).context("Failed to write config file")
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).context("Failed to serialize config")
+    }
+
+    pub fn set(&mut self, key: ConfigKey, value: &str) -> Result<()> {
+        let invalid = || format!("Invalid value '{}' for {}", value, key);
+        let mut updated = self.clone();
+        match key {
+            ConfigKey::Database => updated.database = Some(PathBuf::from(value)),
+            ConfigKey::DefaultFrequency => {
+                updated.default_frequency = value.parse().with_context(invalid)?
+            }
+            ConfigKey::UpcomingDays => {
+                updated.upcoming_days = value.parse().with_context(invalid)?
+            }
+            ConfigKey::DateFormat => updated.date_format = value.to_string(),
+            ConfigKey::DueStyle => {
+                updated.due_style = DueStyle::from_str(value).with_context(invalid)?
+            }
+            ConfigKey::Format => {
+                updated.format = OutputFormat::from_str(value).with_context(invalid)?
+            }
+        }
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(profile) = &self.profile {
+            profile::check_name(profile)?;
+        }
+        DueDisplay::new(self.due_style, &self.date_format)?;
+        Ok(())
+    }
+
+    /// Where the database lives, `$XDG_DATA_HOME/friendgrow/friendgrow.db` unless configured.
+    pub fn database_path(&self) -> Result<PathBuf> {
+        match &self.database {
+            Some(database) => Ok(expand_home(database, &home_dir()?)),
+            None => Ok(Self::data_dir()?.join(DB_FILE)),
+        }
+    }
+
+    pub fn data_dir() -> Result<PathBuf> {
+        Ok(xdg_dir("XDG_DATA_HOME", ".local/share")?.join(APP_DIR))
+    }
+}
+
+fn home_dir() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("HOME must be set to find the config and database"))
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> Result<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        // The XDG spec says to ignore relative paths
+        Some(dir) if dir.is_absolute() => Ok(dir),
+        _ => Ok(home_dir()?.join(fallback)),
+    }
+}
+
+fn expand_home(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: Config = toml::from_str("upcoming_days = 14\ndue_style = \"both\"").unwrap();
+        assert_eq!(config.upcoming_days, 14);
+        assert_eq!(config.due_style, DueStyle::Both);
+        assert_eq!(config.default_frequency, DEFAULT_FREQUENCY);
+
+        let old: Config = toml::from_str("default_freq_weeks = 4").unwrap();
+        assert_eq!(old.default_frequency, Frequency::weeks(4));
+        assert_eq!(config.format, OutputFormat::Table);
+
+        assert!(toml::from_str::<Config>("upcoming = 14").is_err());
+        assert!(toml::from_str::<Config>("format = \"yaml\"").is_err());
+    }
+
+    #[test]
+    fn test_load_lenient() {
+        let path = env::temp_dir().join(format!("friendgrow-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "upcoming_days = \"soon\"\nformat = \"json\"\nupcoming = 14\ndate_format = \"%Q\"\n",
+        )
+        .unwrap();
+        assert!(Config::load(&path).is_err());
+        let config = Config::load_lenient(&path);
+
+        fs::write(&path, "format = ").unwrap();
+        let unparsable = Config::load_lenient(&path);
+        fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.upcoming_days, UP_SOON_CUTOFF_DAYS);
+        assert_eq!(config.date_format, DEFAULT_DUE_DATE_FORMAT);
+        assert!(unparsable.is_err());
+    }
+
+    #[test]
+    fn test_set() {
+        let mut config = Config::default();
+        config.set(ConfigKey::Format, "json").unwrap();
+        config.set(ConfigKey::DefaultFrequency, "1m").unwrap();
+        config.set(ConfigKey::Database, "~/friends.db").unwrap();
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.default_frequency.to_string(), "1 month");
+
+        assert!(config.set(ConfigKey::DefaultFrequency, "often").is_err());
+        assert!(config.set(ConfigKey::DefaultFrequency, "0w").is_err());
+        assert!(config.set(ConfigKey::DateFormat, "%Q").is_err());
+        assert_eq!(config.default_frequency.to_string(), "1 month");
+
+        config
+            .location_aliases
+            .insert("NYC".to_string(), "New York".to_string());
+        let round_trip: Config = toml::from_str(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(round_trip.database, Some(PathBuf::from("~/friends.db")));
+        assert_eq!(round_trip.location_aliases["NYC"], "New York");
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = Path::new("/home/sam");
+        assert_eq!(
+            expand_home(Path::new("~/friends.db"), home),
+            PathBuf::from("/home/sam/friends.db")
+        );
+        assert_eq!(
+            expand_home(Path::new("/srv/friends.db"), home),
+            PathBuf::from("/srv/friends.db")
+        );
+    }
+}
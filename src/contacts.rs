@@ -0,0 +1,100 @@
+use anyhow::Result;
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::str::FromStr;
+use strum::{Display, EnumString, EnumVariantNames, IntoStaticStr};
+
+const PHONE_PUNCTUATION: [char; 6] = ['+', '-', '(', ')', '.', ' '];
+
+#[derive(
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Display,
+    EnumString,
+    EnumVariantNames,
+    IntoStaticStr,
+)]
+#[sql_type = "Text"]
+#[serde(rename_all = "snake_case")]
+pub enum ContactKind {
+    #[strum(serialize = "phone")]
+    Phone,
+    #[strum(serialize = "email")]
+    Email,
+    /// A username somewhere, like `@sam@hobbiton.social` or `signal: sam.42`
+    #[strum(serialize = "handle")]
+    Handle,
+}
+
+impl ToSql<Text, Sqlite> for ContactKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+        let kind: &'static str = self.into();
+        <str as ToSql<Text, Sqlite>>::to_sql(kind, out)
+    }
+}
+
+impl FromSql<Text, Sqlite> for ContactKind {
+    fn from_sql(bytes: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
+        let kind = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(ContactKind::from_str(&kind)?)
+    }
+}
+
+/// The value trimmed, if it looks like the kind of contact it's meant to be.
+pub fn check_value(kind: ContactKind, value: &str) -> Result<String> {
+    let value = value.trim();
+    let valid = match kind {
+        ContactKind::Phone => {
+            value.chars().any(|c| c.is_ascii_digit())
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || PHONE_PUNCTUATION.contains(&c))
+        }
+        ContactKind::Email => match value.split_once('@') {
+            Some((user, domain)) => {
+                !user.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace)
+            }
+            None => false,
+        },
+        ContactKind::Handle => !value.is_empty(),
+    };
+    if !valid {
+        return Err(anyhow::anyhow!("'{}' is not a valid {}", value, kind));
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_value() {
+        assert_eq!(
+            check_value(ContactKind::Phone, " +44 (0)7700 900-123 ").unwrap(),
+            "+44 (0)7700 900-123"
+        );
+        assert!(check_value(ContactKind::Phone, "call me").is_err());
+        assert!(check_value(ContactKind::Phone, "+-").is_err());
+
+        assert!(check_value(ContactKind::Email, "sam@bagend.me").is_ok());
+        assert!(check_value(ContactKind::Email, "sam@localhost").is_err());
+        assert!(check_value(ContactKind::Email, "@bagend.me").is_err());
+        assert!(check_value(ContactKind::Email, "sam gamgee@bagend.me").is_err());
+
+        assert!(check_value(ContactKind::Handle, "@sam@hobbiton.social").is_ok());
+        assert!(check_value(ContactKind::Handle, "  ").is_err());
+    }
+}
@@ -1,8 +1,13 @@
+use crate::frequency::Frequency;
 use anyhow::{Context, Result};
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, Weekday};
+use std::str::FromStr;
 
-const MAX_FREQ_WEEKS: i32 = 52;
 const DATE_FORMAT: &str = "%Y-%m-%d";
+const ACCEPTED_DATES: &str = "YYYY-MM-DD, MM-DD, today, yesterday, a weekday like `tue` or \
+    `last tuesday`, or a time ago like `3 days ago` or `2w ago`";
+const ACCEPTED_FUTURE_DATES: &str = "YYYY-MM-DD, MM-DD, today, tomorrow, a weekday like `fri` \
+    or `next friday`, or a time from now like `in 3 weeks` or `in 2m`";
 
 pub fn parse_date(date: &str) -> Result<NaiveDate> {
     NaiveDate::parse_from_str(date, DATE_FORMAT).context(format!(
@@ -11,22 +16,156 @@ pub fn parse_date(date: &str) -> Result<NaiveDate> {
     ))
 }
 
-pub fn check_frequency(freq_weeks: i32) -> Result<()> {
-    if freq_weeks <= 0 || freq_weeks > MAX_FREQ_WEEKS {
-        return Err(anyhow::anyhow!(
-            "Must see friends between every 1 week and every {} weeks",
-            MAX_FREQ_WEEKS
-        ));
+pub fn parse_date_arg(date: &str) -> Result<NaiveDate> {
+    parse_natural_date(date, local_today())
+}
+
+pub fn parse_natural_date(date: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let lowered = date.trim().to_lowercase();
+    let words: Vec<&str> = lowered.split_whitespace().collect();
+
+    let parsed = match words.as_slice() {
+        ["today"] => Some(today),
+        ["yesterday"] => today.pred_opt(),
+        ["last", day] => parse_weekday(day).map(|day| previous_weekday(day, today)),
+        [amount @ .., "ago"] => parse_time_ago(&amount.join(""), today),
+        [single] => match parse_weekday(single) {
+            Some(day) => Some(previous_weekday(day, today + Duration::days(1))),
+            None => parse_date(single)
+                .ok()
+                .or_else(|| parse_month_day(single).and_then(|md| in_latest_year(md, today))),
+        },
+        _ => None,
+    };
+
+    parsed.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not understand date '{}', use one of: {}",
+            date,
+            ACCEPTED_DATES
+        )
+    })
+}
+
+/// Like `parse_natural_date`, but weekdays and dates without a year mean the next such day.
+pub fn parse_future_date(date: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let lowered = date.trim().to_lowercase();
+    let words: Vec<&str> = lowered.split_whitespace().collect();
+
+    let parsed = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["next", day] => parse_weekday(day).map(|day| next_weekday(day, today)),
+        ["in", amount @ ..] => Frequency::from_str(&amount.join(""))
+            .ok()
+            .and_then(|frequency| frequency.after(today)),
+        [single] => match parse_weekday(single) {
+            Some(day) => Some(next_weekday(day, today - Duration::days(1))),
+            None => parse_date(single)
+                .ok()
+                .or_else(|| parse_month_day(single).and_then(|md| in_next_year(md, today))),
+        },
+        _ => None,
+    };
+
+    parsed.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not understand date '{}', use one of: {}",
+            date,
+            ACCEPTED_FUTURE_DATES
+        )
+    })
+}
+
+/// A date that comes round every year, like a birthday, as an optional year, month and day.
+pub fn parse_yearly_date(date: &str) -> Result<(Option<i32>, u32, u32)> {
+    let date = date.trim();
+    if let Ok(full) = parse_date(date) {
+        return Ok((Some(full.year()), full.month(), full.day()));
     }
-    Ok(())
+    parse_month_day(date)
+        .map(|(month, day)| (None, month, day))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Could not understand date '{}', use YYYY-MM-DD, or MM-DD without a year",
+                date
+            )
+        })
 }
 
-pub fn check_new_seen(new_date: NaiveDate, last_date: Option<String>) -> Result<()> {
-    if last_date.is_some() {
-        let last_date = parse_date(&last_date.unwrap())?;
-        if last_date > new_date {
+pub fn parse_month_day(date: &str) -> Option<(u32, u32)> {
+    let mut parts = date.split(['-', '/']);
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    // Validate against a leap year so that Feb 29 is allowed
+    NaiveDate::from_ymd_opt(2000, month, day).map(|_| (month, day))
+}
+
+fn in_latest_year((month, day): (u32, u32), today: NaiveDate) -> Option<NaiveDate> {
+    (0..=4)
+        .filter_map(|years_back| NaiveDate::from_ymd_opt(today.year() - years_back, month, day))
+        .find(|date| *date <= today)
+}
+
+fn in_next_year((month, day): (u32, u32), today: NaiveDate) -> Option<NaiveDate> {
+    (0..=4)
+        .filter_map(|years_on| NaiveDate::from_ymd_opt(today.year() + years_on, month, day))
+        .find(|date| *date >= today)
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    let weekday = match day {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+fn previous_weekday(day: Weekday, before: NaiveDate) -> NaiveDate {
+    let days_back = (before.weekday().num_days_from_monday() + 7 - day.num_days_from_monday()) % 7;
+    let days_back = if days_back == 0 { 7 } else { days_back };
+    before - Duration::days(days_back as i64)
+}
+
+fn next_weekday(day: Weekday, after: NaiveDate) -> NaiveDate {
+    let days_on = (day.num_days_from_monday() + 7 - after.weekday().num_days_from_monday()) % 7;
+    let days_on = if days_on == 0 { 7 } else { days_on };
+    after + Duration::days(days_on as i64)
+}
+
+fn parse_time_ago(amount: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let unit_start = amount.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = amount.split_at(unit_start);
+    let number: u32 = number.parse().ok()?;
+
+    match unit {
+        "d" | "day" | "days" => today.checked_sub_signed(Duration::days(number as i64)),
+        "w" | "week" | "weeks" => today.checked_sub_signed(Duration::weeks(number as i64)),
+        "m" | "month" | "months" => today.checked_sub_months(Months::new(number)),
+        "y" | "year" | "years" => today.checked_sub_months(Months::new(number.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+pub fn check_new_seen(
+    new_date: NaiveDate,
+    last_date: Option<String>,
+    backfill: bool,
+) -> Result<()> {
+    if let Some(last_date) = last_date {
+        let last_date = parse_date(&last_date)?;
+        if last_date > new_date && !backfill {
             return Err(anyhow::anyhow!(
-                "Already seen more recently on {}",
+                "Already seen more recently on {}, use --backfill to record an earlier visit",
                 last_date
             ));
         }
@@ -39,21 +178,26 @@ pub fn check_new_seen(new_date: NaiveDate, last_date: Option<String>) -> Result<
 }
 
 pub fn local_today() -> NaiveDate {
-    Local::now().date().naive_local()
+    Local::now().date_naive()
+}
+
+/// Parses a YYYY-MM-DD date in tests.
+#[cfg(test)]
+pub fn date(date: &str) -> NaiveDate {
+    parse_date(date).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Duration;
 
     #[test]
     fn test_parse_date() {
-        let correct = NaiveDate::from_ymd(2021, 10, 26);
+        let correct = NaiveDate::from_ymd_opt(2021, 10, 26).unwrap();
         let res = parse_date("2021-10-26");
         assert_eq!(res.unwrap(), correct);
 
-        let correct = NaiveDate::from_ymd(100, 2, 3);
+        let correct = NaiveDate::from_ymd_opt(100, 2, 3).unwrap();
         let res = parse_date("100-2-3");
         assert_eq!(res.unwrap(), correct);
         let res = parse_date("0100-02-03");
@@ -70,56 +214,178 @@ mod tests {
         assert!(parse_date("2021-20-10").is_err());
     }
 
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
     #[test]
-    fn test_check_frequency() {
-        let check = check_frequency(1);
-        assert!(check.is_ok());
-        let check = check_frequency(20);
-        assert!(check.is_ok());
-        let check = check_frequency(52);
-        assert!(check.is_ok());
+    fn test_parse_natural_date_keywords() {
+        // A Wednesday
+        let today = ymd(2021, 12, 8);
+        assert_eq!(parse_natural_date("today", today).unwrap(), today);
+        assert_eq!(
+            parse_natural_date("Yesterday", today).unwrap(),
+            ymd(2021, 12, 7)
+        );
+        assert_eq!(
+            parse_natural_date("2021-10-06", today).unwrap(),
+            ymd(2021, 10, 6)
+        );
     }
 
     #[test]
-    fn test_check_frequency_zero() {
-        let check = check_frequency(0);
-        assert!(check.is_err());
+    fn test_parse_natural_date_weekdays() {
+        // A Wednesday
+        let today = ymd(2021, 12, 8);
+        assert_eq!(parse_natural_date("wed", today).unwrap(), today);
+        assert_eq!(parse_natural_date("tue", today).unwrap(), ymd(2021, 12, 7));
+        assert_eq!(
+            parse_natural_date("thursday", today).unwrap(),
+            ymd(2021, 12, 2)
+        );
+        assert_eq!(
+            parse_natural_date("last wednesday", today).unwrap(),
+            ymd(2021, 12, 1)
+        );
+        assert_eq!(
+            parse_natural_date("last Tuesday", today).unwrap(),
+            ymd(2021, 12, 7)
+        );
     }
 
     #[test]
-    fn test_check_frequency_negative() {
-        let check = check_frequency(-1);
-        assert!(check.is_err());
+    fn test_parse_natural_date_ago() {
+        let today = ymd(2021, 12, 8);
+        assert_eq!(
+            parse_natural_date("3 days ago", today).unwrap(),
+            ymd(2021, 12, 5)
+        );
+        assert_eq!(
+            parse_natural_date("1 day ago", today).unwrap(),
+            ymd(2021, 12, 7)
+        );
+        assert_eq!(
+            parse_natural_date("2w ago", today).unwrap(),
+            ymd(2021, 11, 24)
+        );
+        assert_eq!(
+            parse_natural_date("2 weeks ago", today).unwrap(),
+            ymd(2021, 11, 24)
+        );
+        assert_eq!(
+            parse_natural_date("1m ago", today).unwrap(),
+            ymd(2021, 11, 8)
+        );
     }
 
     #[test]
-    fn test_check_frequency_big() {
-        let check = check_frequency(53);
-        assert!(check.is_err());
+    fn test_parse_natural_date_without_year() {
+        let today = ymd(2021, 12, 8);
+        assert_eq!(
+            parse_natural_date("12-02", today).unwrap(),
+            ymd(2021, 12, 2)
+        );
+        assert_eq!(parse_natural_date("3/14", today).unwrap(), ymd(2021, 3, 14));
+        assert_eq!(
+            parse_natural_date("12-25", today).unwrap(),
+            ymd(2020, 12, 25)
+        );
+        assert_eq!(
+            parse_natural_date("02-29", today).unwrap(),
+            ymd(2020, 2, 29)
+        );
+    }
+
+    #[test]
+    fn test_parse_future_date() {
+        // A Wednesday
+        let today = ymd(2021, 12, 8);
+        assert_eq!(parse_future_date("today", today).unwrap(), today);
+        assert_eq!(
+            parse_future_date("tomorrow", today).unwrap(),
+            ymd(2021, 12, 9)
+        );
+        assert_eq!(parse_future_date("wed", today).unwrap(), today);
+        assert_eq!(parse_future_date("fri", today).unwrap(), ymd(2021, 12, 10));
+        assert_eq!(
+            parse_future_date("next wednesday", today).unwrap(),
+            ymd(2021, 12, 15)
+        );
+        assert_eq!(
+            parse_future_date("in 3 weeks", today).unwrap(),
+            ymd(2021, 12, 29)
+        );
+        assert_eq!(parse_future_date("in 2m", today).unwrap(), ymd(2022, 2, 8));
+        assert_eq!(parse_future_date("01-15", today).unwrap(), ymd(2022, 1, 15));
+        assert_eq!(parse_future_date("12-08", today).unwrap(), today);
+        assert_eq!(
+            parse_future_date("2021-11-01", today).unwrap(),
+            ymd(2021, 11, 1)
+        );
+        assert!(parse_future_date("3 days ago", today).is_err());
+        assert!(parse_future_date("in a while", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_natural_date_errors() {
+        let today = ymd(2021, 12, 8);
+        assert!(parse_natural_date("", today).is_err());
+        assert!(parse_natural_date("someday", today).is_err());
+        assert!(parse_natural_date("13-01", today).is_err());
+        assert!(parse_natural_date("3 fortnights ago", today).is_err());
+        assert!(parse_natural_date("last week", today).is_err());
+        assert!(parse_natural_date("400000000y ago", today).is_err());
+        assert!(parse_natural_date("4294967295 years ago", today).is_err());
     }
 
     #[test]
     fn test_check_new_seen() {
-        let new_date = NaiveDate::from_ymd(102, 2, 5);
-        let check = check_new_seen(new_date, Some("100-2-4".to_string()));
+        let new_date = NaiveDate::from_ymd_opt(102, 2, 5).unwrap();
+        let check = check_new_seen(new_date, Some("100-2-4".to_string()), false);
         assert!(check.is_ok());
-        let check = check_new_seen(new_date, Some("101-12-3".to_string()));
+        let check = check_new_seen(new_date, Some("101-12-3".to_string()), false);
         assert!(check.is_ok());
-        let check = check_new_seen(new_date, None);
+        let check = check_new_seen(new_date, None, false);
         assert!(check.is_ok());
     }
 
     #[test]
     fn test_check_new_seen_earlier() {
-        let new_date = NaiveDate::from_ymd(200, 4, 7);
-        let check = check_new_seen(new_date, Some("200-4-8".to_string()));
+        let new_date = NaiveDate::from_ymd_opt(200, 4, 7).unwrap();
+        let check = check_new_seen(new_date, Some("200-4-8".to_string()), false);
+        assert!(check.is_err());
+    }
+
+    #[test]
+    fn test_check_new_seen_backfill() {
+        let new_date = NaiveDate::from_ymd_opt(200, 4, 7).unwrap();
+        let check = check_new_seen(new_date, Some("200-4-8".to_string()), true);
+        assert!(check.is_ok());
+    }
+
+    #[test]
+    fn test_check_new_seen_backfill_future() {
+        let tomorrow = local_today() + Duration::days(1);
+        let check = check_new_seen(tomorrow, Some("200-4-8".to_string()), true);
         assert!(check.is_err());
     }
 
     #[test]
     fn test_check_new_seen_future() {
         let tomorrow = local_today() + Duration::days(1);
-        let check = check_new_seen(tomorrow, None);
+        let check = check_new_seen(tomorrow, None, false);
         assert!(check.is_err());
     }
+
+    #[test]
+    fn test_parse_yearly_date() {
+        assert_eq!(
+            parse_yearly_date("1990-03-14").unwrap(),
+            (Some(1990), 3, 14)
+        );
+        assert_eq!(parse_yearly_date("02-29").unwrap(), (None, 2, 29));
+        assert_eq!(parse_yearly_date("12/8").unwrap(), (None, 12, 8));
+        assert!(parse_yearly_date("02-30").is_err());
+        assert!(parse_yearly_date("tomorrow").is_err());
+    }
 }
@@ -1,54 +1,206 @@
+use crate::config::Config;
+use crate::frequency::{Frequency, FREQ_DAYS_SQL};
 use crate::models::*;
 use crate::schema::friends::{self, dsl::*};
+use crate::schema::{
+    away_periods, contacts, friend_tags, important_dates, journal, notes, tags, visits,
+};
 use crate::ListOrderBy;
+use crate::{names, profile};
 use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
 pub use diesel::prelude::SqliteConnection;
 use diesel::prelude::*;
-use dotenv::dotenv;
-use std::env;
+use diesel::sql_types::{Bool, Integer, Nullable, Text};
+use diesel::sqlite::Sqlite;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-const DB_KEY: &str = "FRIENDGROW_DB";
+embed_migrations!();
 
-pub fn connect() -> Result<SqliteConnection> {
-    dotenv().ok();
+/// The most recent visit is derived from the visit history rather than stored on the friend.
+const LAST_SEEN_SQL: &str =
+    "(SELECT MAX(visits.date) FROM visits WHERE visits.friend_id = friends.id)";
 
-    let database_url = env::var(DB_KEY).context(
-        "FRIENDGROW_DB must be set in the environment, e.g. 'export FRIENDGROW_DB=~/.friendgrow.db'",
-    )?;
-    SqliteConnection::establish(&database_url).context("Failed to establish connection to database")
+/// Comma separated and sorted, so that tags can be loaded along with each friend.
+const TAGS_SQL: &str = "(SELECT GROUP_CONCAT(name, ',') FROM (
+    SELECT tags.name FROM tags
+    INNER JOIN friend_tags ON friend_tags.tag_id = tags.id
+    WHERE friend_tags.friend_id = friends.id
+    ORDER BY tags.name
+))";
+
+type FriendColumns = (
+    friends::id,
+    friends::name,
+    friends::location,
+    (friends::freq_count, friends::freq_unit),
+    friends::archived,
+    friends::paused_until,
+    SqlLiteral<Nullable<Text>>,
+    SqlLiteral<Nullable<Text>>,
+);
+
+type FriendSqlType = (
+    Integer,
+    Text,
+    Text,
+    (Integer, Text),
+    Bool,
+    Nullable<Text>,
+    Nullable<Text>,
+    Nullable<Text>,
+);
+
+type FriendRowColumns = (
+    friends::id,
+    friends::name,
+    friends::location,
+    (friends::freq_count, friends::freq_unit),
+    friends::archived,
+    friends::paused_until,
+);
+
+fn friend_columns() -> FriendColumns {
+    (
+        id,
+        name,
+        location,
+        (freq_count, freq_unit),
+        archived,
+        paused_until,
+        last_seen(),
+        sql::<Nullable<Text>>(TAGS_SQL),
+    )
+}
+
+fn friend_row_columns() -> FriendRowColumns {
+    (
+        id,
+        name,
+        location,
+        (freq_count, freq_unit),
+        archived,
+        paused_until,
+    )
 }
 
-pub fn load_all_friends(conn: &SqliteConnection) -> QueryResult<Vec<Friend>> {
-    friends::table.load::<Friend>(conn)
+fn last_seen() -> SqlLiteral<Nullable<Text>> {
+    sql::<Nullable<Text>>(LAST_SEEN_SQL)
+}
+
+/// Friends with the tag, if any, leaving out those archived or paused on `active_on` if given.
+fn friends_query(
+    tag: Option<&str>,
+    active_on: Option<NaiveDate>,
+) -> friends::BoxedQuery<'_, Sqlite, FriendSqlType> {
+    let mut query = friends::table.select(friend_columns()).into_boxed();
+    if let Some(today) = active_on {
+        query = query.filter(
+            archived.eq(false).and(
+                paused_until
+                    .is_null()
+                    .or(paused_until.le(today.to_string())),
+            ),
+        );
+    }
+    match tag {
+        Some(tag) => query.filter(
+            id.eq_any(
+                friend_tags::table
+                    .inner_join(tags::table)
+                    .filter(tags::name.eq(tag))
+                    .select(friend_tags::friend_id),
+            ),
+        ),
+        None => query,
+    }
+}
+
+/// The database of the profile chosen with `--profile`, else the one switched to, which
+/// must have been created first so a typo doesn't make a new one.
+pub fn database_path(profile: Option<&str>, config: &Config) -> Result<PathBuf> {
+    let active = profile::active(profile, config);
+    if !profile::exists(&active, config)? {
+        return Err(anyhow::anyhow!(
+            "No profile named {}, create it with `profile create {}`",
+            active,
+            active
+        ));
+    }
+    profile::database_path(&active, config)
+}
+
+/// Open the database, creating it if needed, and bring it up to date.
+pub fn connect(database_path: &Path) -> Result<SqliteConnection> {
+    if let Some(dir) = database_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir).context("Failed to create database directory")?;
+    }
+    let conn = SqliteConnection::establish(&database_path.to_string_lossy())
+        .context("Failed to establish connection to database")?;
+    embedded_migrations::run(&conn).context("Failed to run migration")?;
+    enable_foreign_keys(&conn).context("Failed to enable foreign keys")?;
+    Ok(conn)
+}
+
+/// Foreign keys must stay off while migrations rebuild tables, so enable them afterwards.
+fn enable_foreign_keys(conn: &SqliteConnection) -> QueryResult<()> {
+    conn.execute("PRAGMA foreign_keys = ON").map(|_| ())
+}
+
+pub fn load_all_friends(
+    tag: Option<&str>,
+    active_on: Option<NaiveDate>,
+    conn: &SqliteConnection,
+) -> QueryResult<Vec<Friend>> {
+    friends_query(tag, active_on).load::<Friend>(conn)
 }
 
 pub fn load_all_friends_ordered(
     order_by: ListOrderBy,
     number: Option<i64>,
+    tag: Option<&str>,
+    active_on: Option<NaiveDate>,
     conn: &SqliteConnection,
 ) -> QueryResult<Vec<Friend>> {
-    match order_by {
-        ListOrderBy::Frequency => {
-            let q = friends::table.order_by(freq_weeks);
-            match number {
-                Some(number) => q.limit(number).load::<Friend>(conn),
-                None => q.load::<Friend>(conn),
-            }
-        }
-        ListOrderBy::LastSeen => {
-            let q = friends::table.order_by(last_seen.desc());
-            match number {
-                Some(number) => q.limit(number).load::<Friend>(conn),
-                None => q.load::<Friend>(conn),
-            }
-        }
+    let query = friends_query(tag, active_on);
+    let q = match order_by {
+        ListOrderBy::Frequency => query.order_by(sql::<Integer>(FREQ_DAYS_SQL)),
+        ListOrderBy::LastSeen => query.order_by(last_seen().desc()),
+    };
+    match number {
+        Some(number) => q.limit(number).load::<Friend>(conn),
+        None => q.load::<Friend>(conn),
     }
 }
 
-pub fn load_friend(friend_name: &String, conn: &SqliteConnection) -> QueryResult<Friend> {
-    friends
-        .filter(name.eq(friend_name.clone()))
-        .first::<Friend>(conn)
+pub fn load_all_names(conn: &SqliteConnection) -> QueryResult<Vec<String>> {
+    friends.select(name).load::<String>(conn)
+}
+
+pub fn load_friend(friend_name: &str, conn: &SqliteConnection) -> Result<Friend> {
+    let all_names = load_all_names(conn)?;
+    let found_name = names::resolve(friend_name, &all_names)?;
+    load_friend_named(found_name, conn)
+}
+
+/// Load a friend by their whole name, for commands where guessing could do damage.
+pub fn load_friend_exact(friend_name: &str, conn: &SqliteConnection) -> Result<Friend> {
+    let all_names = load_all_names(conn)?;
+    let found_name = names::resolve_exact(friend_name, &all_names)?;
+    load_friend_named(found_name, conn)
+}
+
+fn load_friend_named(found_name: &str, conn: &SqliteConnection) -> Result<Friend> {
+    let friend = friends_query(None, None)
+        .filter(name.eq(found_name))
+        .first::<Friend>(conn)?;
+    Ok(friend)
 }
 
 pub fn insert_friend(new_friend: NewFriend, conn: &SqliteConnection) -> QueryResult<usize> {
@@ -57,48 +209,513 @@ pub fn insert_friend(new_friend: NewFriend, conn: &SqliteConnection) -> QueryRes
         .execute(conn)
 }
 
-pub fn delete_friend(friend_name: &String, conn: &SqliteConnection) -> QueryResult<usize> {
-    diesel::delete(friends.filter(name.eq(friend_name.clone()))).execute(conn)
+pub fn insert_friends(new_friends: &[NewFriend], conn: &SqliteConnection) -> QueryResult<usize> {
+    conn.transaction(|| {
+        diesel::insert_into(friends::table)
+            .values(new_friends)
+            .execute(conn)
+    })
+}
+
+pub fn delete_friend(friend: &Friend, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(friend).execute(conn)
 }
 
-pub fn update_freq_weeks(
-    friend_name: &String,
-    new_freq_weeks: i32,
+pub fn update_frequency(
+    friend: &Friend,
+    frequency: Frequency,
     conn: &SqliteConnection,
 ) -> QueryResult<usize> {
-    let friend = load_friend(&friend_name, conn)?;
-    diesel::update(&friend)
-        .set(freq_weeks.eq(new_freq_weeks))
+    diesel::update(friend).set(&frequency).execute(conn)
+}
+
+pub fn update_archived(
+    friend: &Friend,
+    is_archived: bool,
+    conn: &SqliteConnection,
+) -> QueryResult<usize> {
+    diesel::update(friend)
+        .set(archived.eq(is_archived))
         .execute(conn)
 }
 
-pub fn update_name(
-    friend_name: &String,
-    new_name: &String,
+pub fn update_paused_until(
+    friend: &Friend,
+    until: String,
     conn: &SqliteConnection,
 ) -> QueryResult<usize> {
-    let friend = load_friend(&friend_name, conn)?;
-    diesel::update(&friend).set(name.eq(new_name)).execute(conn)
+    diesel::update(friend)
+        .set(paused_until.eq(until))
+        .execute(conn)
+}
+
+pub fn update_name(friend: &Friend, new_name: &str, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::update(friend).set(name.eq(new_name)).execute(conn)
 }
 
 pub fn update_location(
-    friend_name: &String,
+    friend: &Friend,
     new_location: String,
     conn: &SqliteConnection,
 ) -> QueryResult<usize> {
-    let friend = load_friend(&friend_name, conn)?;
-    diesel::update(&friend)
+    diesel::update(friend)
         .set(location.eq(new_location))
         .execute(conn)
 }
 
-pub fn update_last_seen(
-    friend_name: &String,
-    new_last_seen: String,
+pub fn load_visits(friend: &Friend, conn: &SqliteConnection) -> QueryResult<Vec<Visit>> {
+    Visit::belonging_to(friend)
+        .order_by((visits::date.desc(), visits::id.desc()))
+        .load::<Visit>(conn)
+}
+
+pub fn visit_exists(
+    friend: &Friend,
+    visit_date: &str,
+    conn: &SqliteConnection,
+) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        Visit::belonging_to(friend).filter(visits::date.eq(visit_date)),
+    ))
+    .get_result(conn)
+}
+
+pub fn insert_visit(
+    friend: &Friend,
+    new_date: String,
+    conn: &SqliteConnection,
+) -> QueryResult<usize> {
+    let new_visit = NewVisit {
+        friend_id: friend.id,
+        date: new_date,
+    };
+    diesel::insert_into(visits::table)
+        .values(&new_visit)
+        .execute(conn)
+}
+
+/// A friend's notes, newest first, all of them unless limited.
+pub fn load_notes(
+    friend: &Friend,
+    limit: Option<i64>,
+    conn: &SqliteConnection,
+) -> QueryResult<Vec<Note>> {
+    let query = Note::belonging_to(friend).order_by((notes::date.desc(), notes::id.desc()));
+    match limit {
+        Some(limit) => query.limit(limit).load::<Note>(conn),
+        None => query.load::<Note>(conn),
+    }
+}
+
+pub fn insert_note(
+    friend: &Friend,
+    note_date: String,
+    note_text: String,
     conn: &SqliteConnection,
 ) -> QueryResult<usize> {
-    let seen_friend = load_friend(&friend_name, conn)?;
-    diesel::update(&seen_friend)
-        .set(last_seen.eq(new_last_seen))
+    let new_note = NewNote {
+        friend_id: friend.id,
+        date: note_date,
+        text: note_text,
+    };
+    diesel::insert_into(notes::table)
+        .values(&new_note)
+        .execute(conn)
+}
+
+pub fn load_dates(friend: &Friend, conn: &SqliteConnection) -> QueryResult<Vec<ImportantDate>> {
+    ImportantDate::belonging_to(friend)
+        .order_by((important_dates::month, important_dates::day))
+        .load::<ImportantDate>(conn)
+}
+
+pub fn insert_date(new_date: &NewImportantDate, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(important_dates::table)
+        .values(new_date)
+        .execute(conn)
+}
+
+pub fn update_date(
+    date_id: i32,
+    new_date: &NewImportantDate,
+    conn: &SqliteConnection,
+) -> QueryResult<usize> {
+    diesel::update(important_dates::table.filter(important_dates::id.eq(date_id)))
+        .set(new_date)
+        .execute(conn)
+}
+
+pub fn delete_date(date_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(important_dates::table.filter(important_dates::id.eq(date_id))).execute(conn)
+}
+
+pub fn load_contacts(friend: &Friend, conn: &SqliteConnection) -> QueryResult<Vec<Contact>> {
+    Contact::belonging_to(friend)
+        .order_by((contacts::kind, contacts::id))
+        .load::<Contact>(conn)
+}
+
+pub fn insert_contact(new_contact: &NewContact, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(contacts::table)
+        .values(new_contact)
         .execute(conn)
 }
+
+pub fn delete_contact(contact_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(contacts::table.filter(contacts::id.eq(contact_id))).execute(conn)
+}
+
+pub fn load_away_periods(conn: &SqliteConnection) -> QueryResult<Vec<AwayPeriod>> {
+    away_periods::table
+        .order_by((away_periods::start_date, away_periods::id))
+        .load::<AwayPeriod>(conn)
+}
+
+/// The time away that `date` falls in, if any.
+pub fn load_away_on(date: NaiveDate, conn: &SqliteConnection) -> QueryResult<Option<AwayPeriod>> {
+    away_periods::table
+        .filter(away_periods::start_date.le(date.to_string()))
+        .filter(away_periods::end_date.ge(date.to_string()))
+        .order_by(away_periods::start_date.desc())
+        .first::<AwayPeriod>(conn)
+        .optional()
+}
+
+pub fn insert_away_periods(rows: &[NewAwayPeriod], conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(away_periods::table)
+        .values(rows)
+        .execute(conn)
+}
+
+pub fn delete_away_period(period_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(away_periods::table.filter(away_periods::id.eq(period_id))).execute(conn)
+}
+
+pub fn insert_tag(friend_id: i32, tag_name: &str, conn: &SqliteConnection) -> QueryResult<usize> {
+    conn.transaction(|| {
+        diesel::insert_or_ignore_into(tags::table)
+            .values(tags::name.eq(tag_name))
+            .execute(conn)?;
+        let found_tag_id = tags::table
+            .filter(tags::name.eq(tag_name))
+            .select(tags::id)
+            .first::<i32>(conn)?;
+        diesel::insert_or_ignore_into(friend_tags::table)
+            .values((
+                friend_tags::friend_id.eq(friend_id),
+                friend_tags::tag_id.eq(found_tag_id),
+            ))
+            .execute(conn)
+    })
+}
+
+pub fn delete_tag(friend: &Friend, tag_name: &str, conn: &SqliteConnection) -> QueryResult<usize> {
+    conn.transaction(|| {
+        let tag_ids = tags::table.filter(tags::name.eq(tag_name)).select(tags::id);
+        let deleted = diesel::delete(
+            friend_tags::table
+                .filter(friend_tags::friend_id.eq(friend.id))
+                .filter(friend_tags::tag_id.eq_any(tag_ids)),
+        )
+        .execute(conn)?;
+
+        // Drop tags that are no longer used by anyone
+        diesel::delete(tags::table.filter(diesel::dsl::not(diesel::dsl::exists(
+            friend_tags::table.filter(friend_tags::tag_id.eq(tags::id)),
+        ))))
+        .execute(conn)?;
+        Ok(deleted)
+    })
+}
+
+pub fn load_friend_rows(conn: &SqliteConnection) -> QueryResult<Vec<FriendRow>> {
+    friends::table
+        .select(friend_row_columns())
+        .order_by(id)
+        .load::<FriendRow>(conn)
+}
+
+pub fn load_all_visits(conn: &SqliteConnection) -> QueryResult<Vec<Visit>> {
+    visits::table.order_by(visits::id).load::<Visit>(conn)
+}
+
+pub fn load_all_notes(conn: &SqliteConnection) -> QueryResult<Vec<Note>> {
+    notes::table.order_by(notes::id).load::<Note>(conn)
+}
+
+pub fn load_all_dates(conn: &SqliteConnection) -> QueryResult<Vec<ImportantDate>> {
+    important_dates::table
+        .order_by(important_dates::id)
+        .load::<ImportantDate>(conn)
+}
+
+pub fn load_all_contacts(conn: &SqliteConnection) -> QueryResult<Vec<Contact>> {
+    contacts::table.order_by(contacts::id).load::<Contact>(conn)
+}
+
+pub fn load_all_tags(conn: &SqliteConnection) -> QueryResult<Vec<Tag>> {
+    tags::table.order_by(tags::id).load::<Tag>(conn)
+}
+
+pub fn load_all_friend_tags(conn: &SqliteConnection) -> QueryResult<Vec<FriendTag>> {
+    friend_tags::table
+        .order_by((friend_tags::friend_id, friend_tags::tag_id))
+        .load::<FriendTag>(conn)
+}
+
+/// Insert a friend, keeping its id when requested, and return the id it was stored with.
+pub fn insert_friend_row(
+    row: &FriendRow,
+    keep_id: bool,
+    conn: &SqliteConnection,
+) -> QueryResult<i32> {
+    if keep_id {
+        diesel::insert_into(friends::table)
+            .values(row)
+            .execute(conn)?;
+    } else {
+        diesel::insert_into(friends::table)
+            .values((
+                name.eq(&row.name),
+                location.eq(&row.location),
+                &row.frequency,
+                archived.eq(row.archived),
+                paused_until.eq(&row.paused_until),
+            ))
+            .execute(conn)?;
+    }
+    friends.filter(name.eq(&row.name)).select(id).first(conn)
+}
+
+pub fn overwrite_friend_row(
+    friend_id: i32,
+    row: &FriendRow,
+    conn: &SqliteConnection,
+) -> QueryResult<usize> {
+    diesel::update(friends.filter(id.eq(friend_id)))
+        .set((
+            location.eq(&row.location),
+            &row.frequency,
+            archived.eq(row.archived),
+            paused_until.eq(&row.paused_until),
+        ))
+        .execute(conn)?;
+    diesel::delete(visits::table.filter(visits::friend_id.eq(friend_id))).execute(conn)?;
+    diesel::delete(notes::table.filter(notes::friend_id.eq(friend_id))).execute(conn)?;
+    diesel::delete(important_dates::table.filter(important_dates::friend_id.eq(friend_id)))
+        .execute(conn)?;
+    diesel::delete(contacts::table.filter(contacts::friend_id.eq(friend_id))).execute(conn)?;
+    diesel::delete(friend_tags::table.filter(friend_tags::friend_id.eq(friend_id))).execute(conn)
+}
+
+pub fn insert_visit_rows(rows: &[RestoredVisit], conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(visits::table)
+        .values(rows)
+        .execute(conn)
+}
+
+pub fn insert_note_rows(rows: &[RestoredNote], conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(notes::table).values(rows).execute(conn)
+}
+
+pub fn insert_date_rows(rows: &[NewImportantDate], conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(important_dates::table)
+        .values(rows)
+        .execute(conn)
+}
+
+pub fn insert_contact_rows(rows: &[NewContact], conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(contacts::table)
+        .values(rows)
+        .execute(conn)
+}
+
+pub fn insert_journal_entry(entry: NewJournalEntry, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_into(journal::table)
+        .values(&entry)
+        .execute(conn)
+}
+
+/// Keep only the most recent entries, so the journal doesn't grow forever.
+pub fn prune_journal(keep: i64, conn: &SqliteConnection) -> QueryResult<usize> {
+    let kept = journal::table
+        .select(journal::id)
+        .order_by(journal::id.desc())
+        .limit(keep);
+    diesel::delete(journal::table.filter(diesel::dsl::not(journal::id.eq_any(kept)))).execute(conn)
+}
+
+/// Forget what was undone, which can't be redone once something else has changed.
+pub fn delete_undone_entries(conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(journal::table.filter(journal::undone.eq(true))).execute(conn)
+}
+
+pub fn load_journal(conn: &SqliteConnection) -> QueryResult<Vec<JournalEntry>> {
+    journal::table
+        .order_by(journal::id.desc())
+        .load::<JournalEntry>(conn)
+}
+
+pub fn last_done_entry(conn: &SqliteConnection) -> QueryResult<Option<JournalEntry>> {
+    journal::table
+        .filter(journal::undone.eq(false))
+        .order_by(journal::id.desc())
+        .first::<JournalEntry>(conn)
+        .optional()
+}
+
+pub fn first_undone_entry(conn: &SqliteConnection) -> QueryResult<Option<JournalEntry>> {
+    journal::table
+        .filter(journal::undone.eq(true))
+        .order_by(journal::id)
+        .first::<JournalEntry>(conn)
+        .optional()
+}
+
+pub fn update_undone(entry_id: i32, undone: bool, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::update(journal::table.filter(journal::id.eq(entry_id)))
+        .set(journal::undone.eq(undone))
+        .execute(conn)
+}
+
+/// Put a friend back exactly as in `row`, whether or not they still exist.
+pub fn restore_friend_row(row: &FriendRow, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(friends.filter(id.eq(row.id)))
+        .set((
+            name.eq(&row.name),
+            location.eq(&row.location),
+            &row.frequency,
+            archived.eq(row.archived),
+            paused_until.eq(&row.paused_until),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(friends::table)
+            .values(row)
+            .execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_visit(visit: &Visit, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(visits::table.filter(visits::id.eq(visit.id)))
+        .set((
+            visits::friend_id.eq(visit.friend_id),
+            visits::date.eq(&visit.date),
+            visits::created_at.eq(&visit.created_at),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(visits::table)
+            .values(visit)
+            .execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_note(note: &Note, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(notes::table.filter(notes::id.eq(note.id)))
+        .set((
+            notes::friend_id.eq(note.friend_id),
+            notes::date.eq(&note.date),
+            notes::text.eq(&note.text),
+            notes::created_at.eq(&note.created_at),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(notes::table).values(note).execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_date(date: &ImportantDate, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(important_dates::table.filter(important_dates::id.eq(date.id)))
+        .set((
+            important_dates::friend_id.eq(date.friend_id),
+            important_dates::label.eq(&date.label),
+            important_dates::year.eq(date.year),
+            important_dates::month.eq(date.month),
+            important_dates::day.eq(date.day),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(important_dates::table)
+            .values(date)
+            .execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_contact(contact: &Contact, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(contacts::table.filter(contacts::id.eq(contact.id)))
+        .set((
+            contacts::friend_id.eq(contact.friend_id),
+            contacts::kind.eq(contact.kind),
+            contacts::label.eq(&contact.label),
+            contacts::value.eq(&contact.value),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(contacts::table)
+            .values(contact)
+            .execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_away_period(period: &AwayPeriod, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(away_periods::table.filter(away_periods::id.eq(period.id)))
+        .set((
+            away_periods::location.eq(&period.location),
+            away_periods::start_date.eq(&period.start_date),
+            away_periods::end_date.eq(&period.end_date),
+        ))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(away_periods::table)
+            .values(period)
+            .execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_tag(tag: &Tag, conn: &SqliteConnection) -> QueryResult<usize> {
+    let updated = diesel::update(tags::table.filter(tags::id.eq(tag.id)))
+        .set(tags::name.eq(&tag.name))
+        .execute(conn)?;
+    match updated {
+        0 => diesel::insert_into(tags::table).values(tag).execute(conn),
+        updated => Ok(updated),
+    }
+}
+
+pub fn restore_friend_tag(friend_tag: &FriendTag, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::insert_or_ignore_into(friend_tags::table)
+        .values(friend_tag)
+        .execute(conn)
+}
+
+pub fn delete_friend_row(friend_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(friends.filter(id.eq(friend_id))).execute(conn)
+}
+
+pub fn delete_visit(visit_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(visits::table.filter(visits::id.eq(visit_id))).execute(conn)
+}
+
+pub fn delete_note(note_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(notes::table.filter(notes::id.eq(note_id))).execute(conn)
+}
+
+pub fn delete_tag_row(tag_id: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(tags::table.filter(tags::id.eq(tag_id))).execute(conn)
+}
+
+pub fn delete_friend_tag(friend_tag: &FriendTag, conn: &SqliteConnection) -> QueryResult<usize> {
+    diesel::delete(
+        friend_tags::table
+            .filter(friend_tags::friend_id.eq(friend_tag.friend_id))
+            .filter(friend_tags::tag_id.eq(friend_tag.tag_id)),
+    )
+    .execute(conn)
+}
@@ -0,0 +1,303 @@
+use crate::schema::friends;
+use anyhow::Result;
+use chrono::{Days, Months, NaiveDate};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use strum::{Display, EnumString, IntoStaticStr};
+
+/// About ten years, beyond which it's hardly keeping in touch.
+const MAX_FREQ_DAYS: i64 = 3653;
+const ACCEPTED_FREQUENCIES: &str = "a number of days, weeks, months or years like `4d`, `3w`, \
+    `2m` or `1y`, a number of weeks, or `daily`, `weekly`, `fortnightly`, `monthly`, \
+    `quarterly` or `yearly`";
+
+/// Orders friends by roughly how many days apart they are seen, to match `approx_days`.
+pub const FREQ_DAYS_SQL: &str = "(freq_count * CASE freq_unit \
+    WHEN 'day' THEN 1 WHEN 'week' THEN 7 WHEN 'month' THEN 30 ELSE 365 END)";
+
+#[derive(
+    AsExpression,
+    FromSqlRow,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Display,
+    EnumString,
+    IntoStaticStr,
+)]
+#[sql_type = "Text"]
+pub enum FreqUnit {
+    #[strum(serialize = "day")]
+    Day,
+    #[strum(serialize = "week")]
+    Week,
+    #[strum(serialize = "month")]
+    Month,
+    #[strum(serialize = "year")]
+    Year,
+}
+
+impl FreqUnit {
+    fn approx_days(self) -> i64 {
+        match self {
+            FreqUnit::Day => 1,
+            FreqUnit::Week => 7,
+            FreqUnit::Month => 30,
+            FreqUnit::Year => 365,
+        }
+    }
+
+    fn abbreviation(self) -> &'static str {
+        match self {
+            FreqUnit::Day => "d",
+            FreqUnit::Week => "w",
+            FreqUnit::Month => "m",
+            FreqUnit::Year => "y",
+        }
+    }
+
+    fn parse(unit: &str) -> Option<Self> {
+        let unit = match unit {
+            "d" | "day" | "days" => FreqUnit::Day,
+            "w" | "wk" | "wks" | "week" | "weeks" => FreqUnit::Week,
+            "m" | "mo" | "month" | "months" => FreqUnit::Month,
+            "y" | "yr" | "yrs" | "year" | "years" => FreqUnit::Year,
+            _ => return None,
+        };
+        Some(unit)
+    }
+}
+
+impl ToSql<Text, Sqlite> for FreqUnit {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+        let unit: &'static str = self.into();
+        <str as ToSql<Text, Sqlite>>::to_sql(unit, out)
+    }
+}
+
+impl FromSql<Text, Sqlite> for FreqUnit {
+    fn from_sql(bytes: Option<&<Sqlite as Backend>::RawValue>) -> deserialize::Result<Self> {
+        let unit = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(FreqUnit::from_str(&unit)?)
+    }
+}
+
+/// How often to see a friend, stored as a count and a calendar unit.
+#[derive(Queryable, Insertable, AsChangeset, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[table_name = "friends"]
+pub struct Frequency {
+    #[column_name = "freq_count"]
+    pub count: i32,
+    #[column_name = "freq_unit"]
+    pub unit: FreqUnit,
+}
+
+impl Frequency {
+    pub fn new(count: i32, unit: FreqUnit) -> Result<Self> {
+        let frequency = Self { count, unit };
+        if count <= 0 || frequency.approx_days() > MAX_FREQ_DAYS {
+            return Err(anyhow::anyhow!(
+                "Frequency {} must be more than 0 and at most 10 years",
+                frequency
+            ));
+        }
+        Ok(frequency)
+    }
+
+    pub const fn weeks(count: i32) -> Self {
+        Self {
+            count,
+            unit: FreqUnit::Week,
+        }
+    }
+
+    pub fn approx_days(&self) -> i64 {
+        i64::from(self.count) * self.unit.approx_days()
+    }
+
+    /// When a friend is next due, keeping to the same day of the month where there is one.
+    pub fn after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let count = u32::try_from(self.count).ok()?;
+        match self.unit {
+            FreqUnit::Day => date.checked_add_days(Days::new(count.into())),
+            FreqUnit::Week => date.checked_add_days(Days::new(u64::from(count) * 7)),
+            FreqUnit::Month => date.checked_add_months(Months::new(count)),
+            FreqUnit::Year => date.checked_add_months(Months::new(count.checked_mul(12)?)),
+        }
+    }
+
+    pub fn every(&self) -> String {
+        match self.count {
+            1 => format!("every {}", self.unit),
+            _ => format!("every {}", self),
+        }
+    }
+
+    /// The compact form accepted by `-f`, like `3w`.
+    pub fn short(&self) -> String {
+        format!("{}{}", self.count, self.unit.abbreviation())
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.count {
+            1 => write!(f, "1 {}", self.unit),
+            count => write!(f, "{} {}s", count, self.unit),
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lowered = s.trim().to_lowercase();
+        let (count, unit) = match lowered.as_str() {
+            "daily" => (1, FreqUnit::Day),
+            "weekly" => (1, FreqUnit::Week),
+            "fortnightly" | "biweekly" => (2, FreqUnit::Week),
+            "monthly" => (1, FreqUnit::Month),
+            "quarterly" => (3, FreqUnit::Month),
+            "yearly" | "annually" => (1, FreqUnit::Year),
+            other => {
+                let digits = other
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(other.len());
+                let (count, unit) = other.split_at(digits);
+                let count: Option<i32> = count.parse().ok();
+                // A bare number is weeks, as frequencies always used to be
+                let unit = match unit.trim() {
+                    "" => Some(FreqUnit::Week),
+                    unit => FreqUnit::parse(unit),
+                };
+                match (count, unit) {
+                    (Some(count), Some(unit)) => (count, unit),
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Could not understand frequency '{}', use {}",
+                            s,
+                            ACCEPTED_FREQUENCIES
+                        ))
+                    }
+                }
+            }
+        };
+        Frequency::new(count, unit)
+    }
+}
+
+impl Serialize for Frequency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.short())
+    }
+}
+
+impl<'de> Deserialize<'de> for Frequency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Older snapshots and configs have a number of weeks
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Weeks(i32),
+            Text(String),
+        }
+
+        let frequency = match Stored::deserialize(deserializer)? {
+            Stored::Weeks(weeks) => Frequency::new(weeks, FreqUnit::Week),
+            Stored::Text(text) => Frequency::from_str(&text),
+        };
+        frequency.map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let parse = |s: &str| Frequency::from_str(s).unwrap();
+        assert_eq!(parse("4d"), Frequency::new(4, FreqUnit::Day).unwrap());
+        assert_eq!(parse("3w"), Frequency::weeks(3));
+        assert_eq!(parse("3"), Frequency::weeks(3));
+        assert_eq!(
+            parse("2 months"),
+            Frequency::new(2, FreqUnit::Month).unwrap()
+        );
+        assert_eq!(parse("1Y"), Frequency::new(1, FreqUnit::Year).unwrap());
+        assert_eq!(
+            parse("monthly"),
+            Frequency::new(1, FreqUnit::Month).unwrap()
+        );
+        assert_eq!(parse("fortnightly"), Frequency::weeks(2));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Frequency::from_str("0w").is_err());
+        assert!(Frequency::from_str("-1w").is_err());
+        assert!(Frequency::from_str("11y").is_err());
+        assert!(Frequency::from_str("3 fortnights").is_err());
+        assert!(Frequency::from_str("often").is_err());
+        assert!(Frequency::from_str("").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Frequency::weeks(3).to_string(), "3 weeks");
+        assert_eq!(Frequency::weeks(1).every(), "every week");
+        assert_eq!(
+            Frequency::new(2, FreqUnit::Year).unwrap().every(),
+            "every 2 years"
+        );
+        assert_eq!(Frequency::new(4, FreqUnit::Day).unwrap().short(), "4d");
+    }
+
+    #[test]
+    fn test_after() {
+        let month = Frequency::new(1, FreqUnit::Month).unwrap();
+        assert_eq!(month.after(ymd(2021, 1, 31)), Some(ymd(2021, 2, 28)));
+        assert_eq!(month.after(ymd(2021, 3, 15)), Some(ymd(2021, 4, 15)));
+
+        let year = Frequency::new(1, FreqUnit::Year).unwrap();
+        assert_eq!(year.after(ymd(2020, 2, 29)), Some(ymd(2021, 2, 28)));
+
+        let days = Frequency::new(4, FreqUnit::Day).unwrap();
+        assert_eq!(days.after(ymd(2021, 12, 30)), Some(ymd(2022, 1, 3)));
+        assert_eq!(
+            Frequency::weeks(2).after(ymd(2021, 4, 1)),
+            Some(ymd(2021, 4, 15))
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let frequency = Frequency::new(2, FreqUnit::Month).unwrap();
+        assert_eq!(serde_json::to_string(&frequency).unwrap(), r#""2m""#);
+        assert_eq!(
+            serde_json::from_str::<Frequency>(r#""2m""#).unwrap(),
+            frequency
+        );
+        assert_eq!(
+            serde_json::from_str::<Frequency>("3").unwrap(),
+            Frequency::weeks(3)
+        );
+        assert!(serde_json::from_str::<Frequency>("0").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::models::{Friend, FriendRecord};
+use crate::upcoming::DueDisplay;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use strum::{Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr};
+
+const MAX_LINE_OCTETS: usize = 75;
+const ICS_DATE_FORMAT: &str = "%Y%m%d";
+const ICS_STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr,
+)]
+pub enum EntryKind {
+    #[strum(serialize = "event")]
+    Event,
+    #[strum(serialize = "todo")]
+    Todo,
+}
+
+pub fn calendar(
+    friends: &[Friend],
+    kind: EntryKind,
+    today: NaiveDate,
+    stamp: DateTime<Utc>,
+) -> Result<String> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//friendgrow//friendgrow {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for friend in friends {
+        // Friends not seen yet are due straight away
+        let due = friend.due_date()?.unwrap_or(today);
+        let record = FriendRecord::new(friend.clone(), today, &DueDisplay::default())?;
+        lines.extend(entry(&record, due, kind, stamp));
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    Ok(lines.iter().map(|l| fold(l) + "\r\n").collect())
+}
+
+fn entry(
+    record: &FriendRecord,
+    due: NaiveDate,
+    kind: EntryKind,
+    stamp: DateTime<Utc>,
+) -> Vec<String> {
+    let friend = &record.friend;
+    let component = match kind {
+        EntryKind::Event => "VEVENT",
+        EntryKind::Todo => "VTODO",
+    };
+    let due_date = due.format(ICS_DATE_FORMAT);
+
+    // The UID only depends on the friend, so re-importing replaces the old entry
+    let mut lines = vec![
+        format!("BEGIN:{}", component),
+        format!("UID:friend-{}@friendgrow", friend.id),
+        format!("DTSTAMP:{}", stamp.format(ICS_STAMP_FORMAT)),
+        format!("SUMMARY:{}", escape(&format!("See {}", friend.name))),
+        format!("DESCRIPTION:{}", escape(&record.to_string())),
+    ];
+    if !friend.location.is_empty() {
+        lines.push(format!("LOCATION:{}", escape(&friend.location)));
+    }
+    if !friend.tags.is_empty() {
+        let tags: Vec<String> = friend.tags.iter().map(|t| escape(t)).collect();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+
+    match kind {
+        EntryKind::Event => {
+            let day_after = (due + Duration::days(1)).format(ICS_DATE_FORMAT);
+            lines.push(format!("DTSTART;VALUE=DATE:{}", due_date));
+            lines.push(format!("DTEND;VALUE=DATE:{}", day_after));
+            lines.push("TRANSP:TRANSPARENT".to_string());
+        }
+        EntryKind::Todo => {
+            lines.push(format!("DUE;VALUE=DATE:{}", due_date));
+            lines.push("STATUS:NEEDS-ACTION".to_string());
+        }
+    }
+
+    lines.push(format!("END:{}", component));
+    lines
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+pub fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sam() -> Friend {
+        Friend::named(7, "Sam")
+            .located("The Shire, Middle Earth")
+            .tagged("hobbits")
+    }
+
+    fn stamp() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2021, 12, 8, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a;b,c\\d\ne"), "a\\;b\\,c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = "x".repeat(80);
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), 75);
+        assert_eq!(parts[1], format!(" {}", "x".repeat(5)));
+        assert_eq!(fold("short"), "short");
+    }
+
+    #[test]
+    fn test_calendar_event() {
+        let today = NaiveDate::from_ymd_opt(2021, 12, 8).unwrap();
+        let ics = calendar(
+            &[sam().seen("2021-12-01")],
+            EntryKind::Event,
+            today,
+            stamp(),
+        )
+        .unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:friend-7@friendgrow\r\n"));
+        assert!(ics.contains("DTSTAMP:20211208T093000Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20211215\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20211216\r\n"));
+        assert!(ics.contains("LOCATION:The Shire\\, Middle Earth\r\n"));
+        assert!(ics.contains("CATEGORIES:hobbits\r\n"));
+        assert!(ics.contains(
+            "DESCRIPTION:Sam (The Shire\\, Middle Earth) every 2 weeks\\, last seen on 202\r\n \
+             1-12-01\\, see next on 12/15\\, tagged hobbits\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_calendar_todo_not_seen() {
+        let today = NaiveDate::from_ymd_opt(2021, 12, 8).unwrap();
+        let ics = calendar(&[sam()], EntryKind::Todo, today, stamp()).unwrap();
+
+        assert!(ics.contains("BEGIN:VTODO\r\n"));
+        assert!(ics.contains("DUE;VALUE=DATE:20211208\r\n"));
+        assert!(!ics.contains("VEVENT"));
+    }
+}
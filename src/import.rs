@@ -0,0 +1,162 @@
+use crate::frequency::Frequency;
+use crate::models::{FriendRow, NewFriend};
+use crate::names;
+use crate::output::Record;
+use prettytable::Row;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use strum::{Display, IntoStaticStr};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Display, IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    #[strum(serialize = "added")]
+    Added,
+    #[strum(serialize = "would add")]
+    WouldAdd,
+    #[strum(serialize = "overwritten")]
+    Overwritten,
+    #[strum(serialize = "would overwrite")]
+    WouldOverwrite,
+    #[strum(serialize = "duplicate")]
+    Duplicate,
+    #[strum(serialize = "missing name")]
+    MissingName,
+}
+
+impl ImportStatus {
+    pub fn dry_run(self) -> Self {
+        match self {
+            ImportStatus::Added => ImportStatus::WouldAdd,
+            ImportStatus::Overwritten => ImportStatus::WouldOverwrite,
+            status => status,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImportRecord {
+    pub name: String,
+    pub location: String,
+    pub frequency: Frequency,
+    pub status: ImportStatus,
+}
+
+impl ImportRecord {
+    pub fn new(friend: &FriendRow, status: ImportStatus) -> Self {
+        Self {
+            name: friend.name.clone(),
+            location: friend.location.clone(),
+            frequency: friend.frequency,
+            status,
+        }
+    }
+}
+
+impl fmt::Display for ImportRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) {}: {}",
+            self.name,
+            self.location,
+            self.frequency.every(),
+            self.status
+        )
+    }
+}
+
+impl Record for ImportRecord {
+    fn table_titles() -> Row {
+        row!["Name", "Location", "Frequency", "Result"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![self.name, self.location, self.frequency, self.status,]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["name", "location", "frequency", "status"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let status: &str = (&self.status).into();
+        vec![
+            self.name.clone(),
+            self.location.clone(),
+            self.frequency.short(),
+            status.replace(' ', "_"),
+        ]
+    }
+}
+
+/// Decide which friends to add, skipping names already taken or repeated in the import,
+/// ignoring case and accents like finding friends by name does.
+pub fn plan(
+    candidates: Vec<(Option<String>, String)>,
+    frequency: Frequency,
+    existing_names: &[String],
+) -> (Vec<NewFriend>, Vec<ImportRecord>) {
+    let mut taken: HashSet<String> = existing_names.iter().map(|n| names::normalize(n)).collect();
+    let mut new_friends = vec![];
+    let mut records = vec![];
+
+    for (name, location) in candidates {
+        let status = match &name {
+            None => ImportStatus::MissingName,
+            Some(name) if !taken.insert(names::normalize(name)) => ImportStatus::Duplicate,
+            Some(name) => {
+                new_friends.push(NewFriend {
+                    name: name.clone(),
+                    location: location.clone(),
+                    frequency,
+                });
+                ImportStatus::WouldAdd
+            }
+        };
+        records.push(ImportRecord {
+            name: name.unwrap_or_default(),
+            location,
+            frequency,
+            status,
+        });
+    }
+
+    (new_friends, records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_skips_duplicates() {
+        let candidates = vec![
+            (Some("Sam".to_string()), "The Shire".to_string()),
+            (Some("Frodo".to_string()), "Hobbiton".to_string()),
+            (None, "Mordor".to_string()),
+            (Some("Frodo".to_string()), "Rivendell".to_string()),
+            (Some("sám".to_string()), "Bree".to_string()),
+        ];
+        let existing = vec!["Sam".to_string()];
+
+        let (new_friends, records) = plan(candidates, Frequency::weeks(4), &existing);
+
+        assert_eq!(new_friends.len(), 1);
+        assert_eq!(new_friends[0].name, "Frodo");
+        assert_eq!(new_friends[0].frequency, Frequency::weeks(4));
+
+        let statuses: Vec<ImportStatus> = records.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ImportStatus::Duplicate,
+                ImportStatus::WouldAdd,
+                ImportStatus::MissingName,
+                ImportStatus::Duplicate,
+                ImportStatus::Duplicate,
+            ]
+        );
+    }
+}
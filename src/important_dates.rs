@@ -0,0 +1,93 @@
+use crate::models::ImportantDate;
+use crate::output::{self, Record};
+use crate::upcoming::{DueDays, DueDisplay};
+use anyhow::Result;
+use chrono::NaiveDate;
+use prettytable::Row;
+use serde::Serialize;
+use std::convert::TryInto;
+use std::fmt;
+
+/// The label `set-birthday` uses, so birthdays can be changed like any other date.
+pub const BIRTHDAY: &str = "Birthday";
+
+/// An important date with when it next comes round.
+#[derive(Serialize, Clone, Debug)]
+pub struct DateRecord {
+    pub label: String,
+    /// As entered, like `1990-03-14`, or `03-14` without a year
+    pub date: String,
+    pub next: NaiveDate,
+    pub days_until: u16,
+    pub turning: Option<i32>,
+    #[serde(skip)]
+    pub display: DueDisplay,
+}
+
+impl DateRecord {
+    pub fn new(date: &ImportantDate, today: NaiveDate, display: &DueDisplay) -> Result<Self> {
+        let next = date
+            .next_on(today)
+            .ok_or_else(|| anyhow::anyhow!("{} on {} is not a valid date", date.label, date))?;
+        Ok(Self {
+            label: date.label.clone(),
+            date: date.to_string(),
+            next,
+            days_until: (next - today).num_days().try_into()?,
+            turning: date.years_on(next),
+            display: display.clone(),
+        })
+    }
+
+    /// When it next comes round, like "on 03/14" or "in 5 days".
+    pub fn describe_next(&self) -> String {
+        self.display
+            .describe(&DueDays::DueIn(self.days_until), Some(self.next))
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for DateRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} on {}, next {}",
+            self.label,
+            self.date,
+            self.describe_next()
+        )?;
+        if let Some(turning) = self.turning {
+            write!(f, ", turning {}", turning)?;
+        }
+        Ok(())
+    }
+}
+
+impl Record for DateRecord {
+    fn table_titles() -> Row {
+        row!["Label", "Date", "Next", "Turning"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![
+            self.label,
+            self.date,
+            self.display.date(self.next),
+            output::optional_field(&self.turning)
+        ]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["label", "date", "next", "days_until", "turning"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.label.clone(),
+            self.date.clone(),
+            self.next.to_string(),
+            self.days_until.to_string(),
+            output::optional_field(&self.turning),
+        ]
+    }
+}
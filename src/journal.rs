@@ -0,0 +1,558 @@
+use crate::db::{self, SqliteConnection};
+use crate::models::{
+    AwayPeriod, Contact, FriendRow, FriendTag, ImportantDate, JournalEntry, NewJournalEntry, Note,
+    Tag, Visit,
+};
+use crate::output::Record;
+use crate::snapshot::{self, Snapshot};
+use anyhow::{Context, Result};
+use diesel::Connection;
+use prettytable::Row;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// How many commands can be undone.
+const JOURNAL_LIMIT: i64 = 100;
+
+/// A row before and after a command, with `None` where it didn't exist.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RowChange<T> {
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+impl<T> RowChange<T> {
+    /// The row expected to be there now, and the row to replace it with.
+    fn sides(&self, direction: Direction) -> (&Option<T>, &Option<T>) {
+        match direction {
+            Direction::Undo => (&self.after, &self.before),
+            Direction::Redo => (&self.before, &self.after),
+        }
+    }
+}
+
+/// Every row a command changed, by table.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Changes {
+    #[serde(default)]
+    pub friends: Vec<RowChange<FriendRow>>,
+    #[serde(default)]
+    pub visits: Vec<RowChange<Visit>>,
+    #[serde(default)]
+    pub notes: Vec<RowChange<Note>>,
+    #[serde(default)]
+    pub important_dates: Vec<RowChange<ImportantDate>>,
+    #[serde(default)]
+    pub contacts: Vec<RowChange<Contact>>,
+    #[serde(default)]
+    pub tags: Vec<RowChange<Tag>>,
+    #[serde(default)]
+    pub friend_tags: Vec<RowChange<FriendTag>>,
+    #[serde(default)]
+    pub away_periods: Vec<RowChange<AwayPeriod>>,
+}
+
+trait Keyed: Clone + PartialEq {
+    type Key: Eq + Hash;
+
+    fn key(&self) -> Self::Key;
+}
+
+impl Keyed for FriendRow {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for Visit {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for Note {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for ImportantDate {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for Contact {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for Tag {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for AwayPeriod {
+    type Key = i32;
+
+    fn key(&self) -> i32 {
+        self.id
+    }
+}
+
+impl Keyed for FriendTag {
+    type Key = (i32, i32);
+
+    fn key(&self) -> (i32, i32) {
+        (self.friend_id, self.tag_id)
+    }
+}
+
+fn by_key<T: Keyed>(rows: &[T]) -> HashMap<T::Key, &T> {
+    rows.iter().map(|row| (row.key(), row)).collect()
+}
+
+fn diff<T: Keyed>(before: &[T], after: &[T]) -> Vec<RowChange<T>> {
+    let before_by_key = by_key(before);
+    let after_by_key = by_key(after);
+    let mut changes = vec![];
+    for row in before {
+        let now = after_by_key.get(&row.key()).copied();
+        if now != Some(row) {
+            changes.push(RowChange {
+                before: Some(row.clone()),
+                after: now.cloned(),
+            });
+        }
+    }
+    for row in after {
+        if !before_by_key.contains_key(&row.key()) {
+            changes.push(RowChange {
+                before: None,
+                after: Some(row.clone()),
+            });
+        }
+    }
+    changes
+}
+
+/// Whether the rows are still as `direction` expects, so nothing newer gets overwritten.
+fn unchanged<T: Keyed>(changes: &[RowChange<T>], current: &[T], direction: Direction) -> bool {
+    let current = by_key(current);
+    changes.iter().all(|change| {
+        let (expected, _) = change.sides(direction);
+        let key = match (change.before.as_ref(), change.after.as_ref()) {
+            (Some(row), _) | (None, Some(row)) => row.key(),
+            (None, None) => return true,
+        };
+        current.get(&key).copied() == expected.as_ref()
+    })
+}
+
+impl Changes {
+    pub fn between(before: &Snapshot, after: &Snapshot) -> Self {
+        Self {
+            friends: diff(&before.friends, &after.friends),
+            visits: diff(&before.visits, &after.visits),
+            notes: diff(&before.notes, &after.notes),
+            important_dates: diff(&before.important_dates, &after.important_dates),
+            contacts: diff(&before.contacts, &after.contacts),
+            tags: diff(&before.tags, &after.tags),
+            friend_tags: diff(&before.friend_tags, &after.friend_tags),
+            away_periods: diff(&before.away_periods, &after.away_periods),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.friends.is_empty()
+            && self.visits.is_empty()
+            && self.notes.is_empty()
+            && self.important_dates.is_empty()
+            && self.contacts.is_empty()
+            && self.tags.is_empty()
+            && self.friend_tags.is_empty()
+            && self.away_periods.is_empty()
+    }
+
+    /// What changed, like "1 friend, 2 visits".
+    pub fn summary(&self) -> String {
+        let counts = [
+            (self.friends.len(), "friend"),
+            (self.visits.len(), "visit"),
+            (self.notes.len(), "note"),
+            (self.important_dates.len(), "date"),
+            (self.contacts.len(), "contact"),
+            (self.tags.len() + self.friend_tags.len(), "tag"),
+            (self.away_periods.len(), "time away"),
+        ];
+        counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, noun)| match count {
+                1 => format!("1 {}", noun),
+                count => format!("{} {}", count, plural(noun)),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn apply(&self, direction: Direction, conn: &SqliteConnection) -> Result<()> {
+        let current = snapshot::take(conn)?;
+        let unchanged = unchanged(&self.friends, &current.friends, direction)
+            && unchanged(&self.visits, &current.visits, direction)
+            && unchanged(&self.notes, &current.notes, direction)
+            && unchanged(&self.important_dates, &current.important_dates, direction)
+            && unchanged(&self.contacts, &current.contacts, direction)
+            && unchanged(&self.tags, &current.tags, direction)
+            && unchanged(&self.friend_tags, &current.friend_tags, direction)
+            && unchanged(&self.away_periods, &current.away_periods, direction);
+        if !unchanged {
+            return Err(anyhow::anyhow!(
+                "The database has changed since, so this can't be {}",
+                direction.done()
+            ));
+        }
+
+        // Delete children before their parents, then restore parents before their children
+        for change in &self.friend_tags {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_friend_tag(row, conn)?;
+            }
+        }
+        for change in &self.visits {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_visit(row.id, conn)?;
+            }
+        }
+        for change in &self.notes {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_note(row.id, conn)?;
+            }
+        }
+        for change in &self.important_dates {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_date(row.id, conn)?;
+            }
+        }
+        for change in &self.contacts {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_contact(row.id, conn)?;
+            }
+        }
+        for change in &self.tags {
+            if let (Some(row), None) = change.sides(direction) {
+                db::delete_tag_row(row.id, conn)?;
+            }
+        }
+        for change in &self.friends {
+            match change.sides(direction) {
+                (Some(row), None) => db::delete_friend_row(row.id, conn)?,
+                (_, Some(row)) => db::restore_friend_row(row, conn)?,
+                (None, None) => 0,
+            };
+        }
+        for change in &self.tags {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_tag(row, conn)?;
+            }
+        }
+        for change in &self.visits {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_visit(row, conn)?;
+            }
+        }
+        for change in &self.notes {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_note(row, conn)?;
+            }
+        }
+        for change in &self.important_dates {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_date(row, conn)?;
+            }
+        }
+        for change in &self.contacts {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_contact(row, conn)?;
+            }
+        }
+        for change in &self.friend_tags {
+            if let (_, Some(row)) = change.sides(direction) {
+                db::restore_friend_tag(row, conn)?;
+            }
+        }
+        for change in &self.away_periods {
+            match change.sides(direction) {
+                (Some(row), None) => db::delete_away_period(row.id, conn)?,
+                (_, Some(row)) => db::restore_away_period(row, conn)?,
+                (None, None) => 0,
+            };
+        }
+        Ok(())
+    }
+}
+
+/// "times away" rather than "time aways".
+fn plural(noun: &str) -> String {
+    match noun.split_once(' ') {
+        Some((first, rest)) => format!("{}s {}", first, rest),
+        None => format!("{}s", noun),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Undo,
+    Redo,
+}
+
+impl Direction {
+    fn done(self) -> &'static str {
+        match self {
+            Direction::Undo => "undone",
+            Direction::Redo => "redone",
+        }
+    }
+}
+
+/// Run a command that changes the database, journaling the rows it changed so it can be undone.
+pub fn track<T>(
+    command: &str,
+    conn: &SqliteConnection,
+    run: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    conn.transaction::<_, anyhow::Error, _>(|| {
+        let before = snapshot::take(conn).context("Failed to journal changes")?;
+        let result = run()?;
+        write_entry(command, &before, conn).context("Failed to journal changes")?;
+        Ok(result)
+    })
+}
+
+fn write_entry(command: &str, before: &Snapshot, conn: &SqliteConnection) -> Result<()> {
+    let after = snapshot::take(conn)?;
+    let changes = Changes::between(before, &after);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    db::delete_undone_entries(conn)?;
+    let entry = NewJournalEntry {
+        command: command.to_string(),
+        changes: serde_json::to_string(&changes)?,
+    };
+    db::insert_journal_entry(entry, conn)?;
+    db::prune_journal(JOURNAL_LIMIT, conn)?;
+    Ok(())
+}
+
+pub fn undo(conn: &SqliteConnection) -> Result<JournalRecord> {
+    let entry = db::last_done_entry(conn)?.ok_or_else(|| anyhow::anyhow!("Nothing to undo"))?;
+    replay(entry, Direction::Undo, conn)
+}
+
+pub fn redo(conn: &SqliteConnection) -> Result<JournalRecord> {
+    let entry = db::first_undone_entry(conn)?.ok_or_else(|| anyhow::anyhow!("Nothing to redo"))?;
+    replay(entry, Direction::Redo, conn)
+}
+
+fn replay(
+    entry: JournalEntry,
+    direction: Direction,
+    conn: &SqliteConnection,
+) -> Result<JournalRecord> {
+    let undone = direction == Direction::Undo;
+    conn.transaction::<_, anyhow::Error, _>(|| {
+        let changes: Changes =
+            serde_json::from_str(&entry.changes).context("Failed to read journal entry")?;
+        changes.apply(direction, conn)?;
+        db::update_undone(entry.id, undone, conn)?;
+        Ok(())
+    })?;
+    JournalRecord::new(JournalEntry { undone, ..entry })
+}
+
+pub fn list(conn: &SqliteConnection) -> Result<Vec<JournalRecord>> {
+    db::load_journal(conn)?
+        .into_iter()
+        .map(JournalRecord::new)
+        .collect()
+}
+
+#[derive(Serialize, Debug)]
+pub struct JournalRecord {
+    pub id: i32,
+    pub command: String,
+    pub changed: String,
+    pub undone: bool,
+    pub created_at: String,
+}
+
+impl JournalRecord {
+    fn new(entry: JournalEntry) -> Result<Self> {
+        let changes: Changes =
+            serde_json::from_str(&entry.changes).context("Failed to read journal entry")?;
+        Ok(Self {
+            id: entry.id,
+            command: entry.command,
+            changed: changes.summary(),
+            undone: entry.undone,
+            created_at: entry.created_at,
+        })
+    }
+}
+
+impl fmt::Display for JournalRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.undone { "Undone" } else { "Done" };
+        write!(
+            f,
+            "{}: `{}`, changing {} (at {})",
+            state, self.command, self.changed, self.created_at
+        )
+    }
+}
+
+impl Record for JournalRecord {
+    fn table_titles() -> Row {
+        row!["#", "Command", "Changed", "At", "Undone"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![
+            self.id,
+            self.command,
+            self.changed,
+            self.created_at,
+            if self.undone { "*" } else { "" }
+        ]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "command", "changed", "undone", "created_at"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.command.clone(),
+            self.changed.clone(),
+            self.undone.to_string(),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let before = vec![
+            FriendRow::named(1, "Sam").located("Here"),
+            FriendRow::named(2, "Sam").located("There"),
+            FriendRow::named(3, "Sam").located("Gone"),
+        ];
+        let after = vec![
+            FriendRow::named(1, "Sam").located("Here"),
+            FriendRow::named(2, "Sam").located("Moved"),
+            FriendRow::named(4, "Sam").located("New"),
+        ];
+        let changes = diff(&before, &after);
+
+        assert_eq!(
+            changes,
+            vec![
+                RowChange {
+                    before: Some(FriendRow::named(2, "Sam").located("There")),
+                    after: Some(FriendRow::named(2, "Sam").located("Moved")),
+                },
+                RowChange {
+                    before: Some(FriendRow::named(3, "Sam").located("Gone")),
+                    after: None,
+                },
+                RowChange {
+                    before: None,
+                    after: Some(FriendRow::named(4, "Sam").located("New")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let changes = diff(
+            &[FriendRow::named(1, "Sam").located("Here")],
+            &[FriendRow::named(1, "Sam").located("There")],
+        );
+        assert!(unchanged(
+            &changes,
+            &[FriendRow::named(1, "Sam").located("There")],
+            Direction::Undo
+        ));
+        assert!(!unchanged(
+            &changes,
+            &[FriendRow::named(1, "Sam").located("Elsewhere")],
+            Direction::Undo
+        ));
+        assert!(unchanged(
+            &changes,
+            &[FriendRow::named(1, "Sam").located("Here")],
+            Direction::Redo
+        ));
+        assert!(!unchanged(&changes, &[], Direction::Redo));
+    }
+
+    #[test]
+    fn test_summary() {
+        let changes = Changes {
+            friends: diff(&[], &[FriendRow::named(1, "Sam").located("Here")]),
+            visits: vec![],
+            notes: vec![],
+            important_dates: vec![],
+            contacts: vec![],
+            tags: vec![],
+            away_periods: diff(
+                &[],
+                &[AwayPeriod {
+                    id: 1,
+                    location: "Bree".to_string(),
+                    start_date: "2021-12-01".to_string(),
+                    end_date: "2021-12-31".to_string(),
+                }],
+            ),
+            friend_tags: diff(
+                &[],
+                &[
+                    FriendTag {
+                        friend_id: 1,
+                        tag_id: 1,
+                    },
+                    FriendTag {
+                        friend_id: 1,
+                        tag_id: 2,
+                    },
+                ],
+            ),
+        };
+        assert_eq!(changes.summary(), "1 friend, 2 tags, 1 time away");
+        assert_eq!(plural("time away"), "times away");
+        assert!(Changes::default().is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+// The diesel 1.x macros expand to impls nested inside functions
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 #[macro_use]
@@ -6,21 +9,41 @@ extern crate diesel_migrations;
 extern crate prettytable;
 
 mod commands;
+mod config;
+mod contacts;
 mod dates;
 mod db;
+mod frequency;
+mod ics;
+mod import;
+mod important_dates;
+mod journal;
 mod models;
+mod names;
+mod notes;
+mod output;
+mod profile;
 mod schema;
+mod snapshot;
+mod stats;
+mod trip;
+mod tui;
 mod upcoming;
+mod vcard;
 
 use crate::commands::*;
 use anyhow::{Context, Result};
+use config::{Config, ConfigKey};
+use contacts::ContactKind;
 use db::SqliteConnection;
-use diesel_migrations::embed_migrations;
+use frequency::Frequency;
+use output::{Output, OutputFormat};
+use snapshot::MergeStrategy;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use structopt::StructOpt;
 use strum::{Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr, VariantNames};
-
-embed_migrations!();
+use upcoming::{DueDisplay, DueStyle, UpcomingOptions, UpcomingSort};
 
 #[derive(Debug, Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr)]
 pub enum ListOrderBy {
@@ -33,11 +56,61 @@ pub enum ListOrderBy {
 fn main() -> Result<()> {
     let opt = FriendGrow::from_args();
 
-    let conn = db::connect().context("Failed to connect to database")?;
-    embedded_migrations::run(&conn).context("Failed to run migration")?;
+    let config_path = Config::path(opt.config)?;
+    // `config` makes do with what it can load, so it can show and fix a broken file
+    let config = match opt.command {
+        Subcommand::Config(_) => Config::load_lenient(&config_path)?,
+        _ => Config::load(&config_path)?,
+    };
+    if let Some(profile) = &opt.profile {
+        profile::check_name(profile)?;
+    }
+
+    let format = match opt.format {
+        Some(format) => OutputFormat::from_str(&format)?,
+        None => config.format,
+    };
+    let due_style = match opt.due_style {
+        Some(due_style) => DueStyle::from_str(&due_style)?,
+        None => config.due_style,
+    };
+    let date_format = opt.date_format.as_deref().unwrap_or(&config.date_format);
+    let out = Output::new(format, DueDisplay::new(due_style, date_format)?);
 
-    execute_command(opt, &conn)?;
-    Ok(())
+    match opt.command {
+        Subcommand::Config(command) => {
+            let database_path = db::database_path(opt.profile.as_deref(), &config)?;
+            execute_config_command(command, &config_path, database_path, &config)
+        }
+        Subcommand::Profile(command) => {
+            let active = profile::active(opt.profile.as_deref(), &config);
+            execute_profile_command(command, &active, &config_path, &config, &out)
+        }
+        Subcommand::Friends(command) => {
+            let database_path = db::database_path(opt.profile.as_deref(), &config)?;
+            let conn = db::connect(&database_path).context("Failed to connect to database")?;
+            let command = command.with_note_written(&conn)?;
+            if command.changes_friends() {
+                journal::track(&command_line(), &conn, || {
+                    execute_command(command, &config, &out, &conn)
+                })
+            } else {
+                execute_command(command, &config, &out, &conn)
+            }
+        }
+    }
+}
+
+/// The command as typed, to show in the journal.
+fn command_line() -> String {
+    std::env::args()
+        .skip(1)
+        .map(|arg| match arg.contains(' ') {
+            true => format!("\"{}\"", arg),
+            false => arg,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[derive(StructOpt, Debug)]
@@ -47,7 +120,61 @@ fn main() -> Result<()> {
     version = "0.2.2",
     author = "annapapitto"
 )]
-enum FriendGrow {
+struct FriendGrow {
+    #[structopt(
+        long,
+        global = true,
+        help = "Config file to use instead of ~/.config/friendgrow/config.toml"
+    )]
+    config: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        global = true,
+        help = "Profile to use instead of the one switched to"
+    )]
+    profile: Option<String>,
+
+    #[structopt(
+        long, global = true, help = "How to print results [default: table]",
+        possible_values=OutputFormat::VARIANTS
+    )]
+    format: Option<String>,
+
+    #[structopt(
+        long, global = true,
+        help = "Whether to show when friends are due as a date, in days or both [default: absolute]",
+        possible_values=DueStyle::VARIANTS
+    )]
+    due_style: Option<String>,
+
+    #[structopt(
+        long,
+        global = true,
+        help = "How to format due dates, using strftime codes [default: %m/%d]"
+    )]
+    date_format: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Subcommand,
+}
+
+#[derive(StructOpt, Debug)]
+enum Subcommand {
+    #[structopt(flatten)]
+    Friends(Command),
+
+    #[structopt(name = "config", about = "Show or change settings in the config file")]
+    Config(ConfigCommand),
+
+    #[structopt(name = "profile", about = "Keep separate lists of friends")]
+    Profile(ProfileCommand),
+}
+
+// Commands run against the profile's database, flattened into the top-level subcommands.
+// A doc comment here would replace the app's about text in --help.
+#[derive(StructOpt, Debug)]
+enum Command {
     #[structopt(name = "list", about = "List all of your friends")]
     ListFriends {
         #[structopt(
@@ -58,6 +185,12 @@ enum FriendGrow {
 
         #[structopt(short, help = "How many friends to list")]
         number: Option<i64>,
+
+        #[structopt(long, help = "Only list friends with this tag")]
+        tag: Option<String>,
+
+        #[structopt(long, help = "Include archived and paused friends")]
+        all: bool,
     },
 
     #[structopt(name = "show", about = "Show a friend")]
@@ -70,13 +203,33 @@ enum FriendGrow {
         #[structopt(help = "Where they live")]
         location: String,
 
-        #[structopt(short, help = "How often to see them, in weeks")]
-        freq_weeks: Option<i32>,
+        #[structopt(short, help = "How often to see them, like 4d, 3w, 2m or 1y")]
+        frequency: Option<Frequency>,
     },
 
     #[structopt(name = "remove", about = "Remove a friend")]
     RemoveFriend { name: String },
 
+    #[structopt(
+        name = "archive",
+        about = "Stop listing a friend, keeping their history"
+    )]
+    ArchiveFriend { name: String },
+
+    #[structopt(name = "unarchive", about = "List an archived friend again")]
+    UnarchiveFriend { name: String },
+
+    #[structopt(name = "pause", about = "Stop listing a friend until a date")]
+    PauseFriend {
+        name: String,
+
+        #[structopt(
+            long,
+            help = "When to list them again, e.g. 2022-01-15, 01-15, next fri, in 3 weeks"
+        )]
+        until: String,
+    },
+
     #[structopt(name = "set-name", about = "Set the name of a friend")]
     SetName {
         name: String,
@@ -97,38 +250,665 @@ enum FriendGrow {
     SetFrequency {
         name: String,
 
-        #[structopt(help = "How often to see them, in weeks")]
-        freq_weeks: i32,
+        #[structopt(help = "How often to see them, like 4d, 3w, 2m or 1y")]
+        frequency: Frequency,
     },
 
-    #[structopt(name = "record", about = "Record seeing a friend")]
+    #[structopt(name = "record", about = "Record seeing friends")]
     RecordSeen {
+        #[structopt(
+            required = true,
+            value_name = "NAMES... DATE",
+            help = "Friends seen together, then the date seen, e.g. Sam Gandolf yesterday. \
+                    Dates can be 2021-10-06, 10-06, today, yesterday, last tue, 3 days ago"
+        )]
+        seen: Vec<String>,
+
+        #[structopt(long, help = "Also record seeing every friend with this tag")]
+        tag: Option<String>,
+
+        #[structopt(long, help = "Allow recording a visit older than the latest one")]
+        backfill: bool,
+
+        #[structopt(long, help = "What you talked about, to remember next time")]
+        note: Option<String>,
+
+        #[structopt(
+            short = "e",
+            long = "edit",
+            conflicts_with = "note",
+            help = "Write the note in $EDITOR"
+        )]
+        edit: bool,
+    },
+
+    #[structopt(
+        name = "note",
+        about = "Write down something to remember about a friend"
+    )]
+    AddNote {
+        name: String,
+
+        #[structopt(help = "The note, written in $EDITOR when left out")]
+        text: Option<String>,
+
+        #[structopt(long, default_value = "today", help = "The day the note is about")]
+        date: String,
+    },
+
+    #[structopt(name = "set-birthday", about = "Set a friend's birthday")]
+    SetBirthday {
         name: String,
 
-        #[structopt(help = "Date seen in YYYY-MM-DD")]
+        #[structopt(help = "e.g. 1990-03-14, or 03-14 without a year")]
         date: String,
     },
 
+    #[structopt(
+        name = "add-date",
+        about = "Add or change a date to remember for a friend, like an anniversary"
+    )]
+    AddDate {
+        name: String,
+
+        #[structopt(help = "What the date is, e.g. Anniversary or \"Rosie's birthday\"")]
+        label: String,
+
+        #[structopt(help = "e.g. 2015-06-20, or 06-20 without a year")]
+        date: String,
+    },
+
+    #[structopt(name = "remove-date", about = "Remove a date from a friend")]
+    RemoveDate { name: String, label: String },
+
+    #[structopt(
+        name = "dates",
+        about = "List a friend's birthday and other important dates"
+    )]
+    ListDates { name: String },
+
+    #[structopt(name = "contact", about = "Show or change how to reach a friend")]
+    ContactFriend {
+        name: String,
+
+        #[structopt(subcommand)]
+        action: Option<ContactAction>,
+    },
+
+    #[structopt(name = "tag", about = "Add tags to a friend")]
+    TagFriend {
+        name: String,
+
+        #[structopt(required = true, help = "Tags to add, e.g. college work")]
+        tags: Vec<String>,
+    },
+
+    #[structopt(name = "untag", about = "Remove tags from a friend")]
+    UntagFriend {
+        name: String,
+
+        #[structopt(required = true, help = "Tags to remove")]
+        tags: Vec<String>,
+    },
+
+    #[structopt(name = "history", about = "List every recorded visit with a friend")]
+    ShowHistory { name: String },
+
+    #[structopt(name = "journal", about = "List every visit and note for a friend")]
+    ShowTimeline { name: String },
+
     #[structopt(name = "upcoming", about = "List friends who are upcoming for a visit")]
-    ListUpcoming {},
+    ListUpcoming {
+        #[structopt(
+            long, help = "Rank by lateness relative to frequency, or by absolute days",
+            possible_values=UpcomingSort::VARIANTS, default_value=UpcomingSort::Urgency.into()
+        )]
+        sort: String,
+
+        #[structopt(
+            long,
+            help = "How many days ahead to look for friends coming due [default: 10]"
+        )]
+        days: Option<u16>,
+
+        #[structopt(long, help = "Leave out friends who aren't overdue yet")]
+        overdue_only: bool,
+
+        #[structopt(long, help = "List friends not seen yet (the default)")]
+        include_unseen: bool,
+
+        #[structopt(
+            long,
+            help = "Leave out friends not seen yet",
+            conflicts_with = "include-unseen"
+        )]
+        exclude_unseen: bool,
+
+        #[structopt(long, help = "Only list friends whose location contains this")]
+        location: Option<String>,
+
+        #[structopt(long, help = "How many friends to list at most")]
+        limit: Option<usize>,
+
+        #[structopt(long, help = "Only list friends with this tag")]
+        tag: Option<String>,
+
+        #[structopt(long, help = "Include archived and paused friends")]
+        all: bool,
+
+        #[structopt(long, help = "List friends everywhere while away")]
+        ignore_travel: bool,
+    },
+
+    #[structopt(name = "stats", about = "Show how well you're keeping up with friends")]
+    Stats {
+        #[structopt(
+            long,
+            help = "Count visits since, e.g. 2021-10-01, 10-01, 3 months ago [default: 90 days ago]"
+        )]
+        since: Option<String>,
+
+        #[structopt(long, help = "Only count friends with this tag")]
+        tag: Option<String>,
+    },
+
+    #[structopt(name = "trip", about = "Plan who to see when visiting somewhere")]
+    PlanTrip {
+        #[structopt(help = "Where you're going, matched against friends' locations")]
+        location: String,
+
+        #[structopt(
+            long,
+            help = "First day there, e.g. 2022-01-15, 01-15, next fri, in 3 weeks [default: today]"
+        )]
+        from: Option<String>,
+
+        #[structopt(long, help = "Last day there [default: the first day]")]
+        to: Option<String>,
+
+        #[structopt(long, help = "How many friends to plan to see each day [default: 2]")]
+        per_day: Option<usize>,
+    },
+
+    #[structopt(
+        name = "away",
+        about = "Record time away, when upcoming lists only friends there"
+    )]
+    Away {
+        #[structopt(
+            help = "Where you'll be, or leave out to list times away",
+            requires = "to"
+        )]
+        location: Option<String>,
+
+        #[structopt(
+            long,
+            help = "First day away, e.g. 2022-01-15, 01-15, next fri, in 3 weeks [default: today]",
+            requires = "location"
+        )]
+        from: Option<String>,
+
+        #[structopt(long, help = "Last day away", requires = "location")]
+        to: Option<String>,
+
+        #[structopt(
+            long,
+            help = "Remove the time away with this ID",
+            conflicts_with = "location"
+        )]
+        remove: Option<i32>,
+    },
+
+    #[structopt(
+        name = "export-ics",
+        about = "Export when friends are due as an iCalendar file"
+    )]
+    ExportIcs {
+        #[structopt(
+            short, long, help = "Whether to export calendar events or to-dos",
+            possible_values=ics::EntryKind::VARIANTS, default_value=ics::EntryKind::Event.into()
+        )]
+        kind: String,
+
+        #[structopt(short, long, help = "File to write to instead of printing")]
+        output: Option<PathBuf>,
+
+        #[structopt(long, help = "Only export friends with this tag")]
+        tag: Option<String>,
+    },
+
+    #[structopt(
+        name = "export-vcard",
+        about = "Export friends with their contact details as a vCard file"
+    )]
+    ExportVcard {
+        #[structopt(help = "Only export this friend")]
+        name: Option<String>,
+
+        #[structopt(short, long, help = "File to write to instead of printing")]
+        output: Option<PathBuf>,
+
+        #[structopt(
+            long,
+            help = "Only export friends with this tag",
+            conflicts_with = "name"
+        )]
+        tag: Option<String>,
+    },
+
+    #[structopt(name = "import-vcard", about = "Add friends from a vCard file")]
+    ImportVcard {
+        #[structopt(help = "vCard file to import, version 3.0 or 4.0")]
+        file: PathBuf,
+
+        #[structopt(
+            short,
+            long = "freq",
+            help = "How often to see them, like 4d, 3w, 2m or 1y"
+        )]
+        frequency: Option<Frequency>,
+
+        #[structopt(long, help = "Show who would be added without adding them")]
+        dry_run: bool,
+    },
+
+    #[structopt(
+        name = "export",
+        about = "Export the whole database as a JSON snapshot"
+    )]
+    Export {
+        #[structopt(short, long, help = "File to write to instead of printing")]
+        output: Option<PathBuf>,
+    },
+
+    #[structopt(name = "import", about = "Import a JSON snapshot made by `export`")]
+    Import {
+        file: PathBuf,
+
+        #[structopt(
+            long, help = "What to do with friends who already exist",
+            possible_values=MergeStrategy::VARIANTS, default_value=MergeStrategy::Fail.into()
+        )]
+        merge: String,
+
+        #[structopt(long, help = "Show what would be imported without importing")]
+        dry_run: bool,
+    },
+
+    #[structopt(name = "import-csv", about = "Add friends from a CSV spreadsheet")]
+    ImportCsv {
+        #[structopt(help = "CSV file with a header row")]
+        file: PathBuf,
+
+        #[structopt(
+            long = "map",
+            number_of_values = 1,
+            help = "Which column holds a field, e.g. 'name=Full Name'. \
+                Fields are name, location, frequency, last_seen and tags"
+        )]
+        mappings: Vec<String>,
+
+        #[structopt(
+            short,
+            long = "freq",
+            help = "How often to see them when not in the file"
+        )]
+        frequency: Option<Frequency>,
+
+        #[structopt(
+            long, help = "What to do with friends who already exist",
+            possible_values=MergeStrategy::VARIANTS, default_value=MergeStrategy::Skip.into()
+        )]
+        merge: String,
+
+        #[structopt(long, help = "Show what would be imported without importing")]
+        dry_run: bool,
+    },
+
+    #[structopt(
+        name = "tui",
+        about = "Review and record friends in an interactive screen"
+    )]
+    Tui {
+        #[structopt(long, help = "Review friends everywhere while away")]
+        ignore_travel: bool,
+    },
+
+    #[structopt(name = "undo", about = "Undo the last command that changed friends")]
+    Undo {
+        #[structopt(long, help = "List the commands that can be undone instead")]
+        list: bool,
+    },
+
+    #[structopt(name = "redo", about = "Redo the last command undone")]
+    Redo {},
+}
+
+impl Command {
+    /// Write the note in the editor when asked to, once the friends it's about are found,
+    /// so a mistyped name doesn't lose the note and no transaction waits on the editor.
+    fn with_note_written(self, conn: &SqliteConnection) -> Result<Self> {
+        match self {
+            Command::RecordSeen {
+                seen,
+                tag,
+                backfill,
+                edit: true,
+                ..
+            } => {
+                let (date, names) = seen
+                    .split_last()
+                    .expect("structopt requires at least the date");
+                let hint = seen_note_hint(names, date, tag.clone(), conn)?;
+                Ok(Command::RecordSeen {
+                    seen,
+                    tag,
+                    backfill,
+                    note: notes::from_editor(&hint)?,
+                    edit: false,
+                })
+            }
+            Command::AddNote {
+                name,
+                text: None,
+                date,
+            } => {
+                let hint = note_hint(&name, &date, conn)?;
+                Ok(Command::AddNote {
+                    text: notes::from_editor(&hint)?,
+                    name,
+                    date,
+                })
+            }
+            command => Ok(command),
+        }
+    }
+
+    /// Whether the command can change friends, and so should be journaled for `undo`.
+    fn changes_friends(&self) -> bool {
+        matches!(
+            self,
+            Command::AddFriend { .. }
+                | Command::RemoveFriend { .. }
+                | Command::ArchiveFriend { .. }
+                | Command::UnarchiveFriend { .. }
+                | Command::PauseFriend { .. }
+                | Command::SetName { .. }
+                | Command::SetLocation { .. }
+                | Command::SetFrequency { .. }
+                | Command::RecordSeen { .. }
+                | Command::AddNote { .. }
+                | Command::SetBirthday { .. }
+                | Command::AddDate { .. }
+                | Command::RemoveDate { .. }
+                | Command::ContactFriend {
+                    action: Some(_),
+                    ..
+                }
+                | Command::TagFriend { .. }
+                | Command::UntagFriend { .. }
+                | Command::Away {
+                    location: Some(_),
+                    ..
+                }
+                | Command::Away {
+                    remove: Some(_),
+                    ..
+                }
+                | Command::ImportVcard { .. }
+                | Command::Import { .. }
+                | Command::ImportCsv { .. }
+        )
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum ContactAction {
+    #[structopt(name = "add", about = "Add a phone number, email or handle")]
+    Add {
+        #[structopt(possible_values = ContactKind::VARIANTS)]
+        kind: String,
+
+        #[structopt(help = "e.g. \"+44 7700 900123\", sam@bagend.me or @sam@hobbiton.social")]
+        value: String,
+
+        #[structopt(long, help = "To tell several apart, e.g. home or work")]
+        label: Option<String>,
+    },
+
+    #[structopt(name = "remove", about = "Remove a contact detail")]
+    Remove {
+        #[structopt(help = "The value to remove, or its label")]
+        contact: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum ConfigCommand {
+    #[structopt(name = "show", about = "Show the settings in use")]
+    Show {},
+
+    #[structopt(name = "set", about = "Change a setting in the config file")]
+    Set {
+        #[structopt(possible_values=ConfigKey::VARIANTS)]
+        key: String,
+        value: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+enum ProfileCommand {
+    #[structopt(name = "list", about = "List profiles")]
+    List {},
+
+    #[structopt(name = "create", about = "Create a profile with an empty database")]
+    Create { name: String },
+
+    #[structopt(name = "switch", about = "Use a profile from now on")]
+    Switch { name: String },
+
+    #[structopt(name = "delete", about = "Delete a profile and its database")]
+    Delete { name: String },
 }
 
-fn execute_command(opt: FriendGrow, conn: &SqliteConnection) -> Result<()> {
-    match opt {
-        FriendGrow::ListFriends { order_by, number } => {
-            list_friends(ListOrderBy::from_str(&order_by)?, number, conn)
+fn execute_config_command(
+    command: ConfigCommand,
+    config_path: &Path,
+    database_path: PathBuf,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        ConfigCommand::Show {} => show_config(config_path, database_path, config),
+        ConfigCommand::Set { key, value } => {
+            set_config(config_path, config, ConfigKey::from_str(&key)?, value)
         }
-        FriendGrow::ShowFriend { name } => show_friend(name, conn),
-        FriendGrow::AddFriend {
+    }
+}
+
+fn execute_profile_command(
+    command: ProfileCommand,
+    active: &str,
+    config_path: &Path,
+    config: &Config,
+    out: &Output,
+) -> Result<()> {
+    match command {
+        ProfileCommand::List {} => list_profiles(active, config, out),
+        ProfileCommand::Create { name } => create_profile(name, active, config, out),
+        ProfileCommand::Switch { name } => switch_profile(name, config_path, config, out),
+        ProfileCommand::Delete { name } => delete_profile(name, active, config, out),
+    }
+}
+
+fn execute_command(
+    command: Command,
+    config: &Config,
+    out: &Output,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    match command {
+        Command::ListFriends {
+            order_by,
+            number,
+            tag,
+            all,
+        } => list_friends(
+            ListOrderBy::from_str(&order_by)?,
+            number,
+            tag,
+            all,
+            out,
+            conn,
+        ),
+        Command::ShowFriend { name } => show_friend_details(name, out, conn),
+        Command::AddFriend {
+            name,
+            location,
+            frequency,
+        } => add_friend(
             name,
             location,
-            freq_weeks,
-        } => add_friend(name, location, freq_weeks, conn),
-        FriendGrow::RemoveFriend { name } => remove_friend(name, conn),
-        FriendGrow::SetName { name, new_name } => set_name(name, new_name, conn),
-        FriendGrow::SetLocation { name, location } => set_location(name, location, conn),
-        FriendGrow::SetFrequency { name, freq_weeks } => set_frequency(name, freq_weeks, conn),
-        FriendGrow::RecordSeen { name, date } => record_seen(name, date, conn),
-        FriendGrow::ListUpcoming {} => list_upcoming(conn),
+            frequency.unwrap_or(config.default_frequency),
+            out,
+            conn,
+        ),
+        Command::RemoveFriend { name } => remove_friend(name, out, conn),
+        Command::ArchiveFriend { name } => archive_friend(name, true, out, conn),
+        Command::UnarchiveFriend { name } => archive_friend(name, false, out, conn),
+        Command::PauseFriend { name, until } => pause_friend(name, until, out, conn),
+        Command::SetName { name, new_name } => set_name(name, new_name, out, conn),
+        Command::SetLocation { name, location } => set_location(name, location, out, conn),
+        Command::SetFrequency { name, frequency } => set_frequency(name, frequency, out, conn),
+        Command::RecordSeen {
+            mut seen,
+            tag,
+            backfill,
+            note,
+            ..
+        } => {
+            // The date comes last, after however many names
+            let date = seen.pop().expect("structopt requires at least the date");
+            let note = note.as_deref().and_then(notes::clean);
+            record_seen(seen, date, tag, backfill, note, out, conn)
+        }
+        Command::AddNote { name, text, date } => add_note(name, text, date, out, conn),
+        Command::ShowHistory { name } => show_history(name, out, conn),
+        Command::ShowTimeline { name } => show_timeline(name, out, conn),
+        Command::SetBirthday { name, date } => set_birthday(name, date, out, conn),
+        Command::AddDate { name, label, date } => set_date(name, label, date, out, conn),
+        Command::RemoveDate { name, label } => remove_date(name, label, out, conn),
+        Command::ListDates { name } => list_dates(name, out, conn),
+        Command::ContactFriend { name, action } => match action {
+            None => show_contacts(name, out, conn),
+            Some(ContactAction::Add { kind, value, label }) => {
+                add_contact(name, ContactKind::from_str(&kind)?, value, label, out, conn)
+            }
+            Some(ContactAction::Remove { contact }) => remove_contact(name, contact, out, conn),
+        },
+        Command::TagFriend { name, tags } => tag_friend(name, tags, out, conn),
+        Command::UntagFriend { name, tags } => untag_friend(name, tags, out, conn),
+        Command::ListUpcoming {
+            sort,
+            days,
+            overdue_only,
+            include_unseen,
+            exclude_unseen,
+            location,
+            limit,
+            tag,
+            all,
+            ignore_travel,
+        } => {
+            let options = UpcomingOptions {
+                sort: UpcomingSort::from_str(&sort)?,
+                days: days.unwrap_or(config.upcoming_days),
+                overdue_only,
+                include_unseen: include_unseen || !exclude_unseen,
+                location,
+                limit,
+                away: match ignore_travel {
+                    true => None,
+                    false => current_away(&config.location_aliases, conn)?,
+                },
+            };
+            list_upcoming(options, tag, all, out, conn)
+        }
+        Command::Stats { since, tag } => show_stats(since, tag, out, conn),
+        Command::PlanTrip {
+            location,
+            from,
+            to,
+            per_day,
+        } => plan_trip(
+            location,
+            from,
+            to,
+            per_day.unwrap_or(trip::DEFAULT_PER_DAY),
+            &config.location_aliases,
+            out,
+            conn,
+        ),
+        Command::ExportIcs { kind, output, tag } => {
+            export_ics(ics::EntryKind::from_str(&kind)?, output, tag, conn)
+        }
+        Command::ExportVcard { name, output, tag } => export_vcard(name, output, tag, conn),
+        Command::ImportVcard {
+            file,
+            frequency,
+            dry_run,
+        } => import_vcard(
+            file,
+            frequency.unwrap_or(config.default_frequency),
+            dry_run,
+            out,
+            conn,
+        ),
+        Command::Export { output } => export_snapshot(output, conn),
+        Command::Import {
+            file,
+            merge,
+            dry_run,
+        } => import_snapshot(file, MergeStrategy::from_str(&merge)?, dry_run, out, conn),
+        Command::ImportCsv {
+            file,
+            mappings,
+            frequency,
+            merge,
+            dry_run,
+        } => import_csv(
+            file,
+            mappings,
+            frequency.unwrap_or(config.default_frequency),
+            MergeStrategy::from_str(&merge)?,
+            dry_run,
+            out,
+            conn,
+        ),
+        Command::Away {
+            location,
+            from,
+            to,
+            remove,
+        } => match (location, remove) {
+            (Some(location), _) => add_away(location, from, to, out, conn),
+            (None, Some(id)) => remove_away(id, out, conn),
+            (None, None) => list_away(out, conn),
+        },
+        Command::Tui { ignore_travel } => {
+            let options = UpcomingOptions {
+                days: config.upcoming_days,
+                away: match ignore_travel {
+                    true => None,
+                    false => current_away(&config.location_aliases, conn)?,
+                },
+                ..Default::default()
+            };
+            run_tui(options, out, conn)
+        }
+        Command::Undo { list } => match list {
+            true => list_journal(out, conn),
+            false => undo(out, conn),
+        },
+        Command::Redo {} => redo(out, conn),
     }
 }
@@ -1,37 +1,75 @@
+use crate::contacts::ContactKind;
 use crate::dates;
-use crate::schema::friends;
-use crate::upcoming::DueDays;
+use crate::frequency::Frequency;
+use crate::output::{self, Record};
+use crate::schema::{
+    away_periods, contacts, friend_tags, friends, important_dates, journal, notes, tags, visits,
+};
+use crate::upcoming::{DueDays, DueDisplay};
 use anyhow::Result;
-use chrono::{Duration, NaiveDate};
+use chrono::{Datelike, NaiveDate};
+use diesel::sql_types::{Nullable, Text};
+use diesel::sqlite::Sqlite;
+use diesel::Queryable;
 use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 use std::fmt;
+use strum::{Display, IntoStaticStr};
 
-#[derive(Identifiable, Queryable, Hash, Eq, PartialEq, Clone, Debug)]
+#[derive(Identifiable, Queryable, Serialize, Hash, Eq, PartialEq, Clone, Debug)]
 pub struct Friend {
     pub id: i32,
     pub name: String,
     pub location: String,
-    pub freq_weeks: i32,
+    pub frequency: Frequency,
+    pub archived: bool,
+    pub paused_until: Option<String>,
     pub last_seen: Option<String>,
+    #[diesel(deserialize_as = "TagList")]
+    pub tags: Vec<String>,
 }
 
 impl Friend {
-    pub fn get_table_titles(with_due: bool) -> Row {
-        let mut r = row!["Name", "Location", "Frequency", "Last seen"];
-        if with_due {
-            r.add_cell(Cell::new("Due"));
+    pub fn get_table_titles(display: &DueDisplay) -> Row {
+        let mut r = row!["Name", "Location", "Tags", "Frequency", "Last seen"];
+        for title in display.titles() {
+            r.add_cell(Cell::new(title));
         }
         r
     }
 
-    pub fn days_until_due(&self, today: NaiveDate) -> Result<DueDays> {
-        if self.last_seen.is_none() {
-            return Ok(DueDays::NotSeen);
+    pub fn due_date(&self) -> Result<Option<NaiveDate>> {
+        let last_seen = match &self.last_seen {
+            Some(last_seen) => dates::parse_date(last_seen)?,
+            None => return Ok(None),
+        };
+        // A pause restarts the wait, so a friend is due a whole period after it ends
+        let since = match &self.paused_until {
+            Some(paused_until) => last_seen.max(dates::parse_date(paused_until)?),
+            None => last_seen,
+        };
+        let next_due = self
+            .frequency
+            .after(since)
+            .ok_or_else(|| anyhow::anyhow!("{} is too far after {}", self.frequency, since))?;
+        Ok(Some(next_due))
+    }
+
+    pub fn status(&self, today: NaiveDate) -> FriendStatus {
+        let today = today.to_string();
+        match &self.paused_until {
+            _ if self.archived => FriendStatus::Archived,
+            Some(paused_until) if *paused_until > today => FriendStatus::Paused,
+            _ => FriendStatus::Active,
         }
-        let last_seen = dates::parse_date(&self.last_seen.clone().unwrap())?;
-        let weeks_to_next = Duration::weeks(self.freq_weeks as i64);
-        let next_due = last_seen + weeks_to_next;
+    }
+
+    pub fn days_until_due(&self, today: NaiveDate) -> Result<DueDays> {
+        let next_due = match self.due_date()? {
+            Some(next_due) => next_due,
+            None => return Ok(DueDays::NotSeen),
+        };
         let days_until_due = (next_due - today).num_days();
         let due_days = match days_until_due {
             d if d < 0 => {
@@ -46,25 +84,28 @@ impl Friend {
         Ok(due_days)
     }
 
-    pub fn get_table_row(&self) -> Row {
-        row![
+    pub fn get_table_row(&self, due_cells: Vec<String>) -> Row {
+        let mut r = row![
             self.name,
             self.location,
-            format!("{} weeks", self.freq_weeks),
-            self.last_seen.clone().unwrap_or("Never".to_string()),
-        ]
-    }
-
-    pub fn get_table_row_with_due(&self, due_in_days: DueDays) -> Row {
-        let due_on = due_in_days.display_some().unwrap_or_default();
-        let mut r = self.get_table_row();
-        r.add_cell(Cell::new(&due_on));
+            self.tags.join(", "),
+            self.frequency.to_string(),
+            self.last_seen
+                .clone()
+                .unwrap_or_else(|| "Never".to_string()),
+        ];
+        for cell in due_cells {
+            r.add_cell(Cell::new(&cell));
+        }
         r
     }
-}
 
-impl fmt::Display for Friend {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fn write_summary(
+        &self,
+        f: &mut fmt::Formatter,
+        due_on: Option<String>,
+        status: FriendStatus,
+    ) -> fmt::Result {
         let mut seen_str: String = self
             .last_seen
             .clone()
@@ -72,27 +113,606 @@ impl fmt::Display for Friend {
                 format!("last seen on {}", last)
             });
 
-        let days_until_due = self
-            .days_until_due(dates::local_today())
-            .map_err(|_| fmt::Error)?;
-        days_until_due
-            .display_some()
-            .map(|n| seen_str.push_str(&format!(", see next {}", n)));
+        if let Some(n) = due_on {
+            seen_str.push_str(&format!(", see next {}", n));
+        }
+
+        if !self.tags.is_empty() {
+            seen_str.push_str(&format!(", tagged {}", self.tags.join(", ")));
+        }
+
+        match (status, &self.paused_until) {
+            (FriendStatus::Archived, _) => seen_str.push_str(", archived"),
+            (FriendStatus::Paused, Some(paused_until)) => {
+                seen_str.push_str(&format!(", paused until {}", paused_until))
+            }
+            _ => {}
+        }
 
         write!(
             f,
-            "{} ({}) every {} weeks, {}",
-            self.name, self.location, self.freq_weeks, seen_str
+            "{} ({}) {}, {}",
+            self.name,
+            self.location,
+            self.frequency.every(),
+            seen_str
         )
     }
 }
 
+/// Builds friends for tests, in The Shire every 2 weeks and not seen yet unless told otherwise.
+#[cfg(test)]
+impl Friend {
+    pub fn named(id: i32, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            location: "The Shire".to_string(),
+            frequency: Frequency::weeks(2),
+            archived: false,
+            paused_until: None,
+            last_seen: None,
+            tags: vec![],
+        }
+    }
+
+    pub fn located(mut self, location: &str) -> Self {
+        self.location = location.to_string();
+        self
+    }
+
+    pub fn every(mut self, frequency: Frequency) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn seen(mut self, last_seen: &str) -> Self {
+        self.last_seen = Some(last_seen.to_string());
+        self
+    }
+
+    pub fn paused(mut self, until: &str) -> Self {
+        self.paused_until = Some(until.to_string());
+        self
+    }
+
+    pub fn tagged(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+}
+
+impl fmt::Display for Friend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let due = self
+            .days_until_due(dates::local_today())
+            .map_err(|_| fmt::Error)?;
+        let due_date = self.due_date().map_err(|_| fmt::Error)?;
+        let status = self.status(dates::local_today());
+        self.write_summary(f, DueDisplay::default().describe(&due, due_date), status)
+    }
+}
+
+/// Archived and paused friends are left out of `list` and `upcoming` unless asked for.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Display, IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+pub enum FriendStatus {
+    #[strum(serialize = "active")]
+    Active,
+    #[strum(serialize = "paused")]
+    Paused,
+    #[strum(serialize = "archived")]
+    Archived,
+}
+
+pub struct TagList(Option<String>);
+
+impl Queryable<Nullable<Text>, Sqlite> for TagList {
+    type Row = Option<String>;
+
+    fn build(row: Self::Row) -> Self {
+        TagList(row)
+    }
+}
+
+impl From<TagList> for Vec<String> {
+    fn from(tags: TagList) -> Self {
+        tags.0
+            .map(|t| t.split(',').map(String::from).collect())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Insertable)]
 #[table_name = "friends"]
 pub struct NewFriend {
     pub name: String,
     pub location: String,
-    pub freq_weeks: i32,
+    #[diesel(embed)]
+    pub frequency: Frequency,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FriendRecord {
+    #[serde(flatten)]
+    pub friend: Friend,
+    pub due: DueDays,
+    pub due_date: Option<NaiveDate>,
+    pub status: FriendStatus,
+    #[serde(skip)]
+    pub display: DueDisplay,
+}
+
+impl FriendRecord {
+    pub fn new(friend: Friend, today: NaiveDate, display: &DueDisplay) -> Result<Self> {
+        let due = friend.days_until_due(today)?;
+        Self::with_due(friend, due, today, display)
+    }
+
+    pub fn with_due(
+        friend: Friend,
+        due: DueDays,
+        today: NaiveDate,
+        display: &DueDisplay,
+    ) -> Result<Self> {
+        let due_date = friend.due_date()?;
+        let status = friend.status(today);
+        Ok(Self {
+            friend,
+            due,
+            due_date,
+            status,
+            display: display.clone(),
+        })
+    }
+
+    pub fn formatted_due_date(&self) -> String {
+        self.due_date
+            .map(|date| self.display.date(date))
+            .unwrap_or_default()
+    }
+
+    /// The due columns for tables, or why there isn't a due date to worry about.
+    pub fn due_cells(&self) -> Vec<String> {
+        let (date, relative) = match self.status {
+            FriendStatus::Active => (
+                self.formatted_due_date(),
+                self.due.display_some().unwrap_or_default(),
+            ),
+            status => (status.to_string(), status.to_string()),
+        };
+        self.display.cells(date, relative)
+    }
+}
+
+impl fmt::Display for FriendRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let due_on = self.display.describe(&self.due, self.due_date);
+        self.friend.write_summary(f, due_on, self.status)
+    }
+}
+
+impl Record for FriendRecord {
+    fn table_titles() -> Row {
+        Self::table_titles_with(&DueDisplay::default())
+    }
+
+    fn table_titles_with(display: &DueDisplay) -> Row {
+        Friend::get_table_titles(display)
+    }
+
+    fn table_row(&self) -> Row {
+        self.friend.get_table_row(self.due_cells())
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "id",
+            "name",
+            "location",
+            "tags",
+            "frequency",
+            "last_seen",
+            "due_status",
+            "due_days",
+            "due_date",
+            "status",
+            "paused_until",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.friend.id.to_string(),
+            self.friend.name.clone(),
+            self.friend.location.clone(),
+            self.friend.tags.join(";"),
+            self.friend.frequency.short(),
+            output::optional_field(&self.friend.last_seen),
+            self.due.status().to_string(),
+            output::optional_field(&self.due.days()),
+            output::optional_field(&self.due_date),
+            self.status.to_string(),
+            output::optional_field(&self.friend.paused_until),
+        ]
+    }
+}
+
+#[derive(
+    Identifiable,
+    Queryable,
+    Insertable,
+    Associations,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+)]
+#[belongs_to(Friend)]
+pub struct Visit {
+    pub id: i32,
+    pub friend_id: i32,
+    pub date: String,
+    pub created_at: String,
+}
+
+#[cfg(test)]
+impl Visit {
+    pub fn on(id: i32, friend_id: i32, date: &str) -> Self {
+        Self {
+            id,
+            friend_id,
+            date: date.to_string(),
+            created_at: "2021-12-02 21:40:56".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Visit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (recorded at {})", self.date, self.created_at)
+    }
+}
+
+impl Record for Visit {
+    fn table_titles() -> Row {
+        row!["Date", "Recorded at"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![self.date, self.created_at]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "friend_id", "date", "created_at"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.friend_id.to_string(),
+            self.date.clone(),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "visits"]
+pub struct NewVisit {
+    pub friend_id: i32,
+    pub date: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "visits"]
+pub struct RestoredVisit {
+    pub friend_id: i32,
+    pub date: String,
+    pub created_at: String,
+}
+
+/// Something to remember about a friend, written on the day it's about.
+#[derive(
+    Identifiable,
+    Queryable,
+    Insertable,
+    Associations,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+)]
+#[belongs_to(Friend)]
+pub struct Note {
+    pub id: i32,
+    pub friend_id: i32,
+    pub date: String,
+    pub text: String,
+    pub created_at: String,
+}
+
+#[cfg(test)]
+impl Note {
+    pub fn on(id: i32, friend_id: i32, date: &str, text: &str) -> Self {
+        Self {
+            id,
+            friend_id,
+            date: date.to_string(),
+            text: text.to_string(),
+            created_at: "2021-12-02 21:40:56".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.date, self.text)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "notes"]
+pub struct NewNote {
+    pub friend_id: i32,
+    pub date: String,
+    pub text: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "notes"]
+pub struct RestoredNote {
+    pub friend_id: i32,
+    pub date: String,
+    pub text: String,
+    pub created_at: String,
+}
+
+/// A date that comes round every year for a friend, like their birthday.
+#[derive(
+    Identifiable,
+    Queryable,
+    Insertable,
+    Associations,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+)]
+#[belongs_to(Friend)]
+pub struct ImportantDate {
+    pub id: i32,
+    pub friend_id: i32,
+    pub label: String,
+    pub year: Option<i32>,
+    pub month: i32,
+    pub day: i32,
+}
+
+impl ImportantDate {
+    /// The next time the date comes round, from today on. Feb 29 falls on Feb 28 in other years.
+    pub fn next_on(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let (month, day) = (self.month.try_into().ok()?, self.day.try_into().ok()?);
+        let in_year = |year: i32| {
+            NaiveDate::from_ymd_opt(year, month, day)
+                .or_else(|| NaiveDate::from_ymd_opt(year, month, day - 1))
+        };
+        let this_year = in_year(today.year())?;
+        match this_year >= today {
+            true => Some(this_year),
+            false => in_year(today.year() + 1),
+        }
+    }
+
+    /// How many years it will have been on `date`, when the year is known.
+    pub fn years_on(&self, date: NaiveDate) -> Option<i32> {
+        self.year
+            .map(|year| date.year() - year)
+            .filter(|years| *years > 0)
+    }
+}
+
+impl fmt::Display for ImportantDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(year) = self.year {
+            write!(f, "{:04}-", year)?;
+        }
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "important_dates"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewImportantDate {
+    pub friend_id: i32,
+    pub label: String,
+    pub year: Option<i32>,
+    pub month: i32,
+    pub day: i32,
+}
+
+/// A way to reach a friend, like a phone number, labelled to tell several apart.
+#[derive(
+    Identifiable,
+    Queryable,
+    Insertable,
+    Associations,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+)]
+#[belongs_to(Friend)]
+pub struct Contact {
+    pub id: i32,
+    pub friend_id: i32,
+    pub kind: ContactKind,
+    pub label: String,
+    pub value: String,
+}
+
+impl fmt::Display for Contact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.label.as_str() {
+            "" => write!(f, "{}: {}", self.kind, self.value),
+            label => write!(f, "{} ({}): {}", self.kind, label, self.value),
+        }
+    }
+}
+
+impl Record for Contact {
+    fn table_titles() -> Row {
+        row!["Kind", "Label", "Value"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![self.kind, self.label, self.value]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "friend_id", "kind", "label", "value"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.friend_id.to_string(),
+            self.kind.to_string(),
+            self.label.clone(),
+            self.value.clone(),
+        ]
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "contacts"]
+pub struct NewContact {
+    pub friend_id: i32,
+    pub kind: ContactKind,
+    pub label: String,
+    pub value: String,
+}
+
+/// A friend as stored in the friends table, without anything derived from other tables.
+#[derive(Queryable, Insertable, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[table_name = "friends"]
+pub struct FriendRow {
+    pub id: i32,
+    pub name: String,
+    pub location: String,
+    #[diesel(embed)]
+    #[serde(alias = "freq_weeks")]
+    pub frequency: Frequency,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub paused_until: Option<String>,
+}
+
+/// Builds friend rows for tests, like `Friend::named` does friends.
+#[cfg(test)]
+impl FriendRow {
+    pub fn named(id: i32, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            location: "The Shire".to_string(),
+            frequency: Frequency::weeks(2),
+            archived: false,
+            paused_until: None,
+        }
+    }
+
+    pub fn located(mut self, location: &str) -> Self {
+        self.location = location.to_string();
+        self
+    }
+}
+
+#[derive(Queryable, Insertable, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[table_name = "tags"]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Queryable, Insertable, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[table_name = "friend_tags"]
+pub struct FriendTag {
+    pub friend_id: i32,
+    pub tag_id: i32,
+}
+
+/// A time away from home, when `upcoming` puts friends where you are first.
+#[derive(Queryable, Insertable, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[table_name = "away_periods"]
+pub struct AwayPeriod {
+    pub id: i32,
+    pub location: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+impl fmt::Display for AwayPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Away in {} from {} to {}",
+            self.location, self.start_date, self.end_date
+        )
+    }
+}
+
+impl Record for AwayPeriod {
+    fn table_titles() -> Row {
+        row!["ID", "Location", "From", "To"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![self.id, self.location, self.start_date, self.end_date]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["id", "location", "start_date", "end_date"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.location.clone(),
+            self.start_date.clone(),
+            self.end_date.clone(),
+        ]
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "away_periods"]
+pub struct NewAwayPeriod {
+    pub location: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct JournalEntry {
+    pub id: i32,
+    pub command: String,
+    pub changes: String,
+    pub undone: bool,
+    pub created_at: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "journal"]
+pub struct NewJournalEntry {
+    pub command: String,
+    pub changes: String,
 }
 
 #[cfg(test)]
@@ -101,13 +721,16 @@ mod tests {
 
     #[test]
     fn test_get_days_until_due_never_seen() {
-        let today = NaiveDate::from_ymd(2021, 04, 02);
+        let today = NaiveDate::from_ymd_opt(2021, 4, 2).unwrap();
         let friend = Friend {
             id: 1,
             name: "Test".to_string(),
             location: "Nowhere".to_string(),
-            freq_weeks: 2,
+            frequency: Frequency::weeks(2),
+            archived: false,
+            paused_until: None,
             last_seen: None,
+            tags: vec![],
         };
 
         assert_eq!(friend.days_until_due(today).unwrap(), DueDays::NotSeen);
@@ -115,13 +738,16 @@ mod tests {
 
     #[test]
     fn test_get_days_until_due_up_soon() {
-        let today = NaiveDate::from_ymd(2021, 04, 02);
+        let today = NaiveDate::from_ymd_opt(2021, 4, 2).unwrap();
         let friend = Friend {
             id: 1,
             name: "Test".to_string(),
             location: "Nowhere".to_string(),
-            freq_weeks: 2,
+            frequency: Frequency::weeks(2),
+            archived: false,
+            paused_until: None,
             last_seen: Some("2021-04-01".to_string()),
+            tags: vec![],
         };
 
         assert_eq!(friend.days_until_due(today).unwrap(), DueDays::DueIn(13));
@@ -129,15 +755,66 @@ mod tests {
 
     #[test]
     fn test_get_days_until_due_overdue() {
-        let today = NaiveDate::from_ymd(2021, 04, 20);
+        let today = NaiveDate::from_ymd_opt(2021, 4, 20).unwrap();
         let friend = Friend {
             id: 1,
             name: "Test".to_string(),
             location: "Nowhere".to_string(),
-            freq_weeks: 2,
+            frequency: Frequency::weeks(2),
+            archived: false,
+            paused_until: None,
             last_seen: Some("2021-04-01".to_string()),
+            tags: vec![],
         };
 
         assert_eq!(friend.days_until_due(today).unwrap(), DueDays::OverDue(5));
     }
+
+    #[test]
+    fn test_pause() {
+        let today = NaiveDate::from_ymd_opt(2021, 4, 20).unwrap();
+        let mut friend = Friend::named(1, "Test")
+            .seen("2021-04-01")
+            .paused("2021-05-01");
+
+        assert_eq!(friend.status(today), FriendStatus::Paused);
+        assert_eq!(friend.days_until_due(today).unwrap(), DueDays::DueIn(25));
+
+        let after_pause = NaiveDate::from_ymd_opt(2021, 5, 1).unwrap();
+        assert_eq!(friend.status(after_pause), FriendStatus::Active);
+
+        friend.last_seen = Some("2021-05-10".to_string());
+        assert_eq!(friend.days_until_due(today).unwrap(), DueDays::DueIn(34));
+
+        friend.archived = true;
+        assert_eq!(friend.status(after_pause), FriendStatus::Archived);
+    }
+
+    #[test]
+    fn test_important_date_next_on() {
+        let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let birthday = ImportantDate {
+            id: 1,
+            friend_id: 1,
+            label: "Birthday".to_string(),
+            year: Some(1990),
+            month: 3,
+            day: 14,
+        };
+        assert_eq!(birthday.next_on(ymd(2021, 3, 14)), Some(ymd(2021, 3, 14)));
+        assert_eq!(birthday.next_on(ymd(2021, 3, 15)), Some(ymd(2022, 3, 14)));
+        assert_eq!(birthday.years_on(ymd(2022, 3, 14)), Some(32));
+        assert_eq!(birthday.to_string(), "1990-03-14");
+
+        let leap_day = ImportantDate {
+            year: None,
+            month: 2,
+            day: 29,
+            ..birthday
+        };
+        assert_eq!(leap_day.next_on(ymd(2021, 1, 1)), Some(ymd(2021, 2, 28)));
+        assert_eq!(leap_day.next_on(ymd(2023, 3, 1)), Some(ymd(2024, 2, 29)));
+        assert_eq!(leap_day.years_on(ymd(2024, 2, 29)), None);
+        assert_eq!(leap_day.to_string(), "02-29");
+    }
 }
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+pub fn normalize(name: &str) -> String {
+    name.trim()
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = normalize(tag);
+    if tag.is_empty() || tag.contains(',') {
+        return Err(anyhow::anyhow!(
+            "Tag '{}' must not be empty or contain commas",
+            tag
+        ));
+    }
+    Ok(tag)
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = prev_row[j] + usize::from(a_char != *b_char);
+            row[j + 1] = substitution.min(prev_row[j + 1] + 1).min(row[j] + 1);
+        }
+        prev_row = row;
+    }
+    prev_row[b.len()]
+}
+
+/// The friend a name means, which may be a unique prefix or differ in case and accents.
+pub fn resolve<'a>(query: &str, names: &'a [String]) -> Result<&'a str> {
+    find(query, names, true)
+}
+
+/// Like `resolve`, but only the whole name will do, ignoring case and accents, for
+/// commands like `remove` where picking the wrong friend does damage.
+pub fn resolve_exact<'a>(query: &str, names: &'a [String]) -> Result<&'a str> {
+    find(query, names, false)
+}
+
+/// Check a new name isn't empty and can't be mistaken for another friend's, which would
+/// differ only in case or accents. `renaming` is the friend's current name, which they
+/// can keep with different case.
+pub fn check_new(name: &str, names: &[String], renaming: Option<&str>) -> Result<()> {
+    let wanted = normalize(name);
+    if wanted.is_empty() {
+        return Err(anyhow::anyhow!("Friend name must not be empty"));
+    }
+    match names
+        .iter()
+        .find(|n| Some(n.as_str()) != renaming && normalize(n) == wanted)
+    {
+        Some(existing) => Err(anyhow::anyhow!(
+            "There's already a friend named {}",
+            existing
+        )),
+        None => Ok(()),
+    }
+}
+
+fn find<'a>(query: &str, names: &'a [String], allow_prefix: bool) -> Result<&'a str> {
+    if names.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No friends yet. Add some with the `add` command."
+        ));
+    }
+    if let Some(exact) = names.iter().find(|n| n.as_str() == query) {
+        return Ok(exact);
+    }
+
+    let wanted = normalize(query);
+    // An empty name would be a prefix of every friend's
+    if wanted.is_empty() {
+        return Err(anyhow::anyhow!("Friend name must not be empty"));
+    }
+    let normalized: Vec<(String, &String)> = names.iter().map(|n| (normalize(n), n)).collect();
+
+    if let Some((_, name)) = normalized.iter().find(|(n, _)| *n == wanted) {
+        return Ok(name);
+    }
+
+    let prefixed: Vec<&str> = normalized
+        .iter()
+        .filter(|(n, _)| allow_prefix && n.starts_with(&wanted))
+        .map(|(_, name)| name.as_str())
+        .collect();
+    match prefixed.as_slice() {
+        [name] => return Ok(name),
+        [] => {}
+        several => {
+            return Err(anyhow::anyhow!(
+                "'{}' matches several friends: {}",
+                query,
+                several.join(", ")
+            ))
+        }
+    }
+
+    let mut by_distance: Vec<(usize, &str)> = normalized
+        .iter()
+        .map(|(n, name)| (edit_distance(&wanted, n), name.as_str()))
+        .collect();
+    by_distance.sort();
+    let suggestions: Vec<&str> = by_distance
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect();
+
+    Err(anyhow::anyhow!(
+        "No friend named '{}', did you mean: {}?",
+        query,
+        suggestions.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec!["Gandolf", "Sam", "Samwise", "Frodo", "Éowyn"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Gandolf"), "gandolf");
+        assert_eq!(normalize(" Éowyn "), "eowyn");
+        assert_eq!(normalize("Ｓａｍ"), "sam");
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag(" College ").unwrap(), "college");
+        assert!(normalize_tag("").is_err());
+        assert!(normalize_tag("work,college").is_err());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("sam", ""), 3);
+        assert_eq!(edit_distance("gandalf", "gandolf"), 1);
+        assert_eq!(edit_distance("frodo", "fordo"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_resolve_exact() {
+        let names = names();
+        assert_eq!(resolve("Sam", &names).unwrap(), "Sam");
+        assert_eq!(resolve("sam", &names).unwrap(), "Sam");
+        assert_eq!(resolve("eowyn", &names).unwrap(), "Éowyn");
+    }
+
+    #[test]
+    fn test_resolve_prefix() {
+        let names = names();
+        assert_eq!(resolve("gan", &names).unwrap(), "Gandolf");
+        assert_eq!(resolve("samw", &names).unwrap(), "Samwise");
+    }
+
+    #[test]
+    fn test_resolve_empty() {
+        let names = names();
+        assert!(resolve("", &names).is_err());
+        assert!(resolve("  ", &names).is_err());
+        assert!(resolve_exact("", &names).is_err());
+    }
+
+    #[test]
+    fn test_resolve_exact_only() {
+        let names = names();
+        assert_eq!(resolve_exact("SAM", &names).unwrap(), "Sam");
+        assert_eq!(resolve_exact("eowyn", &names).unwrap(), "Éowyn");
+        assert!(resolve_exact("gan", &names).is_err());
+        assert!(resolve_exact("Gandalf", &names).is_err());
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_prefix() {
+        let names = names();
+        let err = resolve("sa", &names).unwrap_err().to_string();
+        assert!(err.contains("Sam, Samwise"));
+    }
+
+    #[test]
+    fn test_resolve_suggestions() {
+        let names = names();
+        let err = resolve("Gandalf", &names).unwrap_err().to_string();
+        assert!(err.starts_with("No friend named 'Gandalf', did you mean: Gandolf"));
+    }
+
+    #[test]
+    fn test_check_new() {
+        let names = names();
+        assert!(check_new("Pippin", &names, None).is_ok());
+        assert!(check_new("sam", &names, None).is_err());
+        assert!(check_new("EOWYN", &names, None).is_err());
+        assert!(check_new(" ", &names, None).is_err());
+        assert!(check_new("SAM", &names, Some("Sam")).is_ok());
+        assert!(check_new("Sam", &names, Some("Frodo")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_no_friends() {
+        assert!(resolve("Sam", &[]).is_err());
+    }
+}
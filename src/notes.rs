@@ -0,0 +1,221 @@
+use crate::models::{Note, Visit};
+use crate::output::Record;
+use anyhow::{Context, Result};
+use prettytable::Row;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many notes `show` prints under a friend.
+pub const RECENT_NOTES: i64 = 3;
+const FALLBACK_EDITOR: &str = "vi";
+const COMMENT: char = '#';
+/// How many names to try for the note file before giving up.
+const NOTE_FILE_ATTEMPTS: u32 = 100;
+
+/// The note's text, or `None` when there's nothing but whitespace.
+pub fn clean(text: &str) -> Option<String> {
+    let text = text.trim();
+    match text.is_empty() {
+        true => None,
+        false => Some(text.to_string()),
+    }
+}
+
+/// Write a note in `$VISUAL` or `$EDITOR`, with `hint` shown as a comment that gets dropped.
+pub fn from_editor(hint: &str) -> Result<Option<String>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| FALLBACK_EDITOR.to_string());
+    let (path, mut file) = create_note_file()?;
+    write!(file, "\n{} {}\n", COMMENT, hint).context("Failed to write note file")?;
+    drop(file);
+
+    // The editor may come with arguments, like `code --wait`
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg(&editor)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run editor {}", editor));
+    let text = fs::read_to_string(&path).context("Failed to read note file");
+    let _ = fs::remove_file(&path);
+
+    if !status?.success() {
+        return Err(anyhow::anyhow!(
+            "Editor {} failed, so no note was saved",
+            editor
+        ));
+    }
+    Ok(clean(&strip_comments(&text?)))
+}
+
+/// A note file that didn't exist before, readable only by you, so nothing planted at the
+/// path beforehand gets written through or read.
+fn create_note_file() -> Result<(PathBuf, fs::File)> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.subsec_nanos());
+    for attempt in 0..NOTE_FILE_ATTEMPTS {
+        let path = env::temp_dir().join(format!(
+            "friendgrow-note-{}-{}.txt",
+            process::id(),
+            nanos.wrapping_add(attempt)
+        ));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).context("Failed to create note file"),
+        }
+    }
+    Err(anyhow::anyhow!("Failed to create note file"))
+}
+
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with(COMMENT))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A day in a friend's timeline: whether they were seen, and what was noted.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TimelineEntry {
+    pub date: String,
+    pub seen: bool,
+    pub notes: Vec<String>,
+}
+
+/// Every day with a visit or a note, newest first.
+pub fn timeline(visits: &[Visit], notes: &[Note]) -> Vec<TimelineEntry> {
+    let mut dates: Vec<&str> = visits
+        .iter()
+        .map(|v| v.date.as_str())
+        .chain(notes.iter().map(|n| n.date.as_str()))
+        .collect();
+    dates.sort_unstable_by(|a, b| b.cmp(a));
+    dates.dedup();
+
+    dates
+        .into_iter()
+        .map(|date| {
+            let mut day_notes: Vec<&Note> = notes.iter().filter(|n| n.date == date).collect();
+            day_notes.sort_by_key(|n| n.id);
+            TimelineEntry {
+                date: date.to_string(),
+                seen: visits.iter().any(|v| v.date == date),
+                notes: day_notes.iter().map(|n| n.text.clone()).collect(),
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date)?;
+        if self.seen {
+            write!(f, " seen")?;
+        }
+        if !self.notes.is_empty() {
+            write!(f, ": {}", self.notes.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Record for TimelineEntry {
+    fn table_titles() -> Row {
+        row!["Date", "Seen", "Notes"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![
+            self.date,
+            if self.seen { "*" } else { "" },
+            self.notes.join("\n")
+        ]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["date", "seen", "notes"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.seen.to_string(),
+            self.notes.join("\n"),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(
+            clean("  Moved to Bree \n"),
+            Some("Moved to Bree".to_string())
+        );
+        assert_eq!(clean(" \n "), None);
+        assert_eq!(
+            strip_comments("Likes hiking\n# Note about Sam\n"),
+            "Likes hiking"
+        );
+    }
+
+    #[test]
+    fn test_timeline() {
+        let visits = vec![Visit::on(1, 1, "2021-10-06"), Visit::on(2, 1, "2021-12-02")];
+        let notes = vec![
+            Note::on(2, 1, "2021-12-02", "Second breakfast"),
+            Note::on(1, 1, "2021-12-02", "Talked about rings"),
+            Note::on(3, 1, "2021-11-15", "New job"),
+        ];
+
+        let entries = timeline(&visits, &notes);
+        assert_eq!(
+            entries,
+            vec![
+                TimelineEntry {
+                    date: "2021-12-02".to_string(),
+                    seen: true,
+                    notes: vec![
+                        "Talked about rings".to_string(),
+                        "Second breakfast".to_string()
+                    ],
+                },
+                TimelineEntry {
+                    date: "2021-11-15".to_string(),
+                    seen: false,
+                    notes: vec!["New job".to_string()],
+                },
+                TimelineEntry {
+                    date: "2021-10-06".to_string(),
+                    seen: true,
+                    notes: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            entries[0].to_string(),
+            "2021-12-02 seen: Talked about rings; Second breakfast"
+        );
+        assert_eq!(entries[1].to_string(), "2021-11-15: New job");
+    }
+}
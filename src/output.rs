@@ -0,0 +1,121 @@
+use crate::upcoming::DueDisplay;
+use anyhow::Result;
+use prettytable::{format, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use strum::{Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr};
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Display,
+    EnumIter,
+    EnumString,
+    EnumVariantNames,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[strum(serialize = "table")]
+    Table,
+    #[strum(serialize = "json")]
+    Json,
+    #[strum(serialize = "csv")]
+    Csv,
+    #[strum(serialize = "tsv")]
+    Tsv,
+    #[strum(serialize = "plain")]
+    Plain,
+}
+
+pub trait Record: Serialize + fmt::Display {
+    fn table_titles() -> Row;
+    /// The titles when the columns depend on how due dates are shown.
+    fn table_titles_with(_display: &DueDisplay) -> Row {
+        Self::table_titles()
+    }
+    fn table_row(&self) -> Row;
+    fn headers() -> Vec<&'static str>;
+    fn fields(&self) -> Vec<String>;
+}
+
+pub struct Output {
+    format: OutputFormat,
+    due_display: DueDisplay,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, due_display: DueDisplay) -> Self {
+        Self {
+            format,
+            due_display,
+        }
+    }
+
+    pub fn due_display(&self) -> &DueDisplay {
+        &self.due_display
+    }
+
+    pub fn is_structured(&self) -> bool {
+        !matches!(self.format, OutputFormat::Table | OutputFormat::Plain)
+    }
+
+    pub fn one<R: Record>(&self, record: &R) -> Result<()> {
+        match self.format {
+            OutputFormat::Table | OutputFormat::Plain => println!("{}", record),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(record)?),
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                self.delimited(std::slice::from_ref(record))?
+            }
+        }
+        Ok(())
+    }
+
+    pub fn many<R: Record>(&self, records: &[R]) -> Result<()> {
+        match self.format {
+            OutputFormat::Table => {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(R::table_titles_with(&self.due_display));
+                for record in records {
+                    table.add_row(record.table_row());
+                }
+                table.printstd();
+            }
+            OutputFormat::Plain => {
+                for record in records {
+                    println!("{}", record);
+                }
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+            OutputFormat::Csv | OutputFormat::Tsv => self.delimited(records)?,
+        }
+        Ok(())
+    }
+
+    fn delimited<R: Record>(&self, records: &[R]) -> Result<()> {
+        let delimiter = match self.format {
+            OutputFormat::Tsv => b'\t',
+            _ => b',',
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(io::stdout());
+
+        writer.write_record(R::headers())?;
+        for record in records {
+            writer.write_record(record.fields())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn optional_field<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
@@ -0,0 +1,160 @@
+use crate::config::Config;
+use crate::output::Record;
+use anyhow::{Context, Result};
+use dotenv::dotenv;
+use prettytable::Row;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The profile using the database from the config file, which existed before profiles did.
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_DIR: &str = "profiles";
+const DB_KEY: &str = "FRIENDGROW_DB";
+const DB_EXTENSION: &str = "db";
+
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Profile name '{}' must only use letters, numbers, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// The profile chosen with `--profile`, else the one switched to, else the default.
+pub fn active(chosen: Option<&str>, config: &Config) -> String {
+    chosen
+        .or(config.profile.as_deref())
+        .unwrap_or(DEFAULT_PROFILE)
+        .to_string()
+}
+
+/// The default profile's database is `FRIENDGROW_DB` when set, else the config file's.
+pub fn database_path(name: &str, config: &Config) -> Result<PathBuf> {
+    check_name(name)?;
+    if name == DEFAULT_PROFILE {
+        dotenv().ok();
+        return match env::var_os(DB_KEY) {
+            Some(database) => Ok(PathBuf::from(database)),
+            None => config.database_path(),
+        };
+    }
+    Ok(profile_file(&profiles_dir()?, name))
+}
+
+pub fn exists(name: &str, config: &Config) -> Result<bool> {
+    Ok(name == DEFAULT_PROFILE || database_path(name, config)?.exists())
+}
+
+/// Every profile with a database, always including the default one.
+pub fn list() -> Result<Vec<String>> {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+    let dir = profiles_dir()?;
+    if dir.exists() {
+        let mut others = vec![];
+        for entry in fs::read_dir(&dir).context("Failed to read profiles")? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(DB_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                if check_name(name).is_ok() && name != DEFAULT_PROFILE {
+                    others.push(name.to_string());
+                }
+            }
+        }
+        others.sort();
+        names.extend(others);
+    }
+    Ok(names)
+}
+
+fn profiles_dir() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join(PROFILES_DIR))
+}
+
+fn profile_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(name).with_extension(DB_EXTENSION)
+}
+
+#[derive(Serialize)]
+pub struct ProfileRecord {
+    pub name: String,
+    pub database: PathBuf,
+    pub active: bool,
+}
+
+impl fmt::Display for ProfileRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.database.display())?;
+        if self.active {
+            write!(f, ", active")?;
+        }
+        Ok(())
+    }
+}
+
+impl Record for ProfileRecord {
+    fn table_titles() -> Row {
+        row!["Profile", "Database", "Active"]
+    }
+
+    fn table_row(&self) -> Row {
+        row![
+            self.name,
+            self.database.display(),
+            if self.active { "*" } else { "" }
+        ]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec!["name", "database", "active"]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.database.display().to_string(),
+            self.active.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("work").is_ok());
+        assert!(check_name("side-projects_2").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("../work").is_err());
+        assert!(check_name("my work").is_err());
+    }
+
+    #[test]
+    fn test_active() {
+        let mut config = Config::default();
+        assert_eq!(active(None, &config), DEFAULT_PROFILE);
+        config.profile = Some("work".to_string());
+        assert_eq!(active(None, &config), "work");
+        assert_eq!(active(Some("home"), &config), "home");
+    }
+
+    #[test]
+    fn test_profile_file() {
+        assert_eq!(
+            profile_file(Path::new("/data/profiles"), "work"),
+            PathBuf::from("/data/profiles/work.db")
+        );
+    }
+}
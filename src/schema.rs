@@ -3,7 +3,99 @@ diesel::table! {
         id -> Integer,
         name -> Text,
         location -> Text,
-        freq_weeks -> Integer,
-        last_seen -> Nullable<Text>,
+        freq_count -> Integer,
+        freq_unit -> Text,
+        archived -> Bool,
+        paused_until -> Nullable<Text>,
     }
 }
+
+diesel::table! {
+    visits (id) {
+        id -> Integer,
+        friend_id -> Integer,
+        date -> Text,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    friend_tags (friend_id, tag_id) {
+        friend_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    notes (id) {
+        id -> Integer,
+        friend_id -> Integer,
+        date -> Text,
+        text -> Text,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    important_dates (id) {
+        id -> Integer,
+        friend_id -> Integer,
+        label -> Text,
+        year -> Nullable<Integer>,
+        month -> Integer,
+        day -> Integer,
+    }
+}
+
+diesel::table! {
+    contacts (id) {
+        id -> Integer,
+        friend_id -> Integer,
+        kind -> Text,
+        label -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    away_periods (id) {
+        id -> Integer,
+        location -> Text,
+        start_date -> Text,
+        end_date -> Text,
+    }
+}
+
+diesel::table! {
+    journal (id) {
+        id -> Integer,
+        command -> Text,
+        changes -> Text,
+        undone -> Bool,
+        created_at -> Text,
+    }
+}
+
+diesel::joinable!(visits -> friends (friend_id));
+diesel::joinable!(notes -> friends (friend_id));
+diesel::joinable!(important_dates -> friends (friend_id));
+diesel::joinable!(contacts -> friends (friend_id));
+diesel::joinable!(friend_tags -> friends (friend_id));
+diesel::joinable!(friend_tags -> tags (tag_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    friends,
+    visits,
+    notes,
+    important_dates,
+    contacts,
+    tags,
+    friend_tags
+);
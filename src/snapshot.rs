@@ -0,0 +1,500 @@
+use crate::db::{self, SqliteConnection};
+use crate::frequency::Frequency;
+use crate::import::{ImportRecord, ImportStatus};
+use crate::models::*;
+use crate::{dates, names};
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, Utc};
+use diesel::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use strum::{Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr};
+
+pub const SNAPSHOT_VERSION: u32 = 4;
+const TAG_SEPARATORS: [char; 2] = [';', ','];
+// Matches SQLite's CURRENT_TIMESTAMP, which visits are stamped with when recorded
+const CREATED_AT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Display, EnumIter, EnumString, EnumVariantNames, IntoStaticStr,
+)]
+pub enum MergeStrategy {
+    #[strum(serialize = "skip")]
+    Skip,
+    #[strum(serialize = "overwrite")]
+    Overwrite,
+    #[strum(serialize = "fail")]
+    Fail,
+}
+
+/// Every table in the database, with rows referring to each other by id.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub version: u32,
+    pub exported_at: String,
+    pub friends: Vec<FriendRow>,
+    #[serde(default)]
+    pub visits: Vec<Visit>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub important_dates: Vec<ImportantDate>,
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub friend_tags: Vec<FriendTag>,
+    #[serde(default)]
+    pub away_periods: Vec<AwayPeriod>,
+}
+
+impl Snapshot {
+    fn tag_names(&self, friend_id: i32) -> Vec<&str> {
+        self.friend_tags
+            .iter()
+            .filter(|ft| ft.friend_id == friend_id)
+            .filter_map(|ft| self.tags.iter().find(|t| t.id == ft.tag_id))
+            .map(|t| t.name.as_str())
+            .collect()
+    }
+}
+
+pub fn take(conn: &SqliteConnection) -> Result<Snapshot> {
+    Ok(Snapshot {
+        version: SNAPSHOT_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        friends: db::load_friend_rows(conn)?,
+        visits: db::load_all_visits(conn)?,
+        notes: db::load_all_notes(conn)?,
+        important_dates: db::load_all_dates(conn)?,
+        contacts: db::load_all_contacts(conn)?,
+        tags: db::load_all_tags(conn)?,
+        friend_tags: db::load_all_friend_tags(conn)?,
+        away_periods: db::load_away_periods(conn)?,
+    })
+}
+
+pub fn restore(
+    snapshot: &Snapshot,
+    merge: MergeStrategy,
+    dry_run: bool,
+    conn: &SqliteConnection,
+) -> Result<Vec<ImportRecord>> {
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(anyhow::anyhow!(
+            "Snapshot version {} is newer than the supported version {}",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        ));
+    }
+
+    let mut records = vec![];
+    let mut rolled_back = false;
+    let result = conn.transaction::<_, anyhow::Error, _>(|| {
+        records = apply(snapshot, merge, conn)?;
+        if dry_run {
+            rolled_back = true;
+            return Err(diesel::result::Error::RollbackTransaction.into());
+        }
+        Ok(())
+    });
+
+    match result {
+        Err(_) if rolled_back => {
+            for record in records.iter_mut() {
+                record.status = record.status.dry_run();
+            }
+            Ok(records)
+        }
+        Err(e) => Err(e),
+        Ok(()) => Ok(records),
+    }
+}
+
+fn apply(
+    snapshot: &Snapshot,
+    merge: MergeStrategy,
+    conn: &SqliteConnection,
+) -> Result<Vec<ImportRecord>> {
+    let existing = db::load_friend_rows(conn)?;
+    // Friends added earlier in the import take ids too, which a later friend may have
+    let mut ids_taken: HashSet<i32> = existing.iter().map(|f| f.id).collect();
+    // Names match ignoring case and accents, like finding friends by name does
+    let mut names_added = HashSet::new();
+    let mut records = vec![];
+
+    for friend in &snapshot.friends {
+        let wanted = names::normalize(&friend.name);
+        if !names_added.insert(wanted.clone()) {
+            records.push(ImportRecord::new(friend, ImportStatus::Duplicate));
+            continue;
+        }
+        let current = existing
+            .iter()
+            .find(|f| names::normalize(&f.name) == wanted);
+        let (friend_id, status) = match (current, merge) {
+            (None, _) => {
+                let keep_id = !ids_taken.contains(&friend.id);
+                let friend_id = db::insert_friend_row(friend, keep_id, conn)?;
+                ids_taken.insert(friend_id);
+                (friend_id, ImportStatus::Added)
+            }
+            (Some(_), MergeStrategy::Fail) => {
+                return Err(anyhow::anyhow!("Friend {} already exists", friend.name));
+            }
+            (Some(_), MergeStrategy::Skip) => {
+                records.push(ImportRecord::new(friend, ImportStatus::Duplicate));
+                continue;
+            }
+            (Some(current), MergeStrategy::Overwrite) => {
+                db::overwrite_friend_row(current.id, friend, conn)?;
+                (current.id, ImportStatus::Overwritten)
+            }
+        };
+
+        let visits: Vec<RestoredVisit> = snapshot
+            .visits
+            .iter()
+            .filter(|v| v.friend_id == friend.id)
+            .map(|v| RestoredVisit {
+                friend_id,
+                date: v.date.clone(),
+                created_at: v.created_at.clone(),
+            })
+            .collect();
+        db::insert_visit_rows(&visits, conn)?;
+
+        let notes: Vec<RestoredNote> = snapshot
+            .notes
+            .iter()
+            .filter(|n| n.friend_id == friend.id)
+            .map(|n| RestoredNote {
+                friend_id,
+                date: n.date.clone(),
+                text: n.text.clone(),
+                created_at: n.created_at.clone(),
+            })
+            .collect();
+        db::insert_note_rows(&notes, conn)?;
+
+        let important_dates: Vec<NewImportantDate> = snapshot
+            .important_dates
+            .iter()
+            .filter(|d| d.friend_id == friend.id)
+            .map(|d| NewImportantDate {
+                friend_id,
+                label: d.label.clone(),
+                year: d.year,
+                month: d.month,
+                day: d.day,
+            })
+            .collect();
+        db::insert_date_rows(&important_dates, conn)?;
+
+        let contacts: Vec<NewContact> = snapshot
+            .contacts
+            .iter()
+            .filter(|c| c.friend_id == friend.id)
+            .map(|c| NewContact {
+                friend_id,
+                kind: c.kind,
+                label: c.label.clone(),
+                value: c.value.clone(),
+            })
+            .collect();
+        db::insert_contact_rows(&contacts, conn)?;
+
+        for tag in snapshot.tag_names(friend.id) {
+            db::insert_tag(friend_id, tag, conn)?;
+        }
+        records.push(ImportRecord::new(friend, status));
+    }
+
+    // Times away aren't anyone's, so only those not already here are added
+    let existing_away = db::load_away_periods(conn)?;
+    let away_periods: Vec<NewAwayPeriod> = snapshot
+        .away_periods
+        .iter()
+        .filter(|p| {
+            !existing_away.iter().any(|e| {
+                e.location == p.location && e.start_date == p.start_date && e.end_date == p.end_date
+            })
+        })
+        .map(|p| NewAwayPeriod {
+            location: p.location.clone(),
+            start_date: p.start_date.clone(),
+            end_date: p.end_date.clone(),
+        })
+        .collect();
+    db::insert_away_periods(&away_periods, conn)?;
+    Ok(records)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, EnumString, EnumVariantNames)]
+pub enum CsvField {
+    #[strum(serialize = "name")]
+    Name,
+    #[strum(serialize = "location")]
+    Location,
+    #[strum(to_string = "frequency", serialize = "freq_weeks")]
+    Frequency,
+    #[strum(serialize = "last_seen")]
+    LastSeen,
+    #[strum(serialize = "tags")]
+    Tags,
+}
+
+/// Parse mappings like `name=Full Name` into which CSV column holds each field.
+pub fn parse_column_mapping(mappings: &[String]) -> Result<HashMap<CsvField, String>> {
+    mappings
+        .iter()
+        .map(|mapping| {
+            let (field, column) = mapping.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Column mapping '{}' must look like field=Column", mapping)
+            })?;
+            let field = CsvField::from_str(field.trim())
+                .with_context(|| format!("Unknown field '{}' in column mapping", field))?;
+            Ok((field, column.trim().to_string()))
+        })
+        .collect()
+}
+
+pub fn from_csv(
+    text: &str,
+    mapping: &HashMap<CsvField, String>,
+    default_frequency: Frequency,
+    today: NaiveDate,
+) -> Result<Snapshot> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+
+    let column_of = |field: CsvField| -> Option<usize> {
+        match mapping.get(&field) {
+            Some(column) => headers.iter().position(|h| h.eq_ignore_ascii_case(column)),
+            // Any of a field's names, so older `freq_weeks` columns still work
+            None => headers
+                .iter()
+                .position(|h| CsvField::from_str(&h.to_lowercase()) == Ok(field)),
+        }
+    };
+    let name_column = column_of(CsvField::Name)
+        .ok_or_else(|| anyhow::anyhow!("No name column found, map one with --map name=<column>"))?;
+    let columns: HashMap<CsvField, usize> = [
+        CsvField::Location,
+        CsvField::Frequency,
+        CsvField::LastSeen,
+        CsvField::Tags,
+    ]
+    .iter()
+    .filter_map(|field| column_of(*field).map(|column| (*field, column)))
+    .collect();
+
+    let mut snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        exported_at: Local::now().to_rfc3339(),
+        ..Default::default()
+    };
+
+    for (row_number, row) in reader.records().enumerate() {
+        let row = row?;
+        let friend_id = row_number as i32 + 1;
+        let cell = |field: CsvField| -> &str {
+            columns
+                .get(&field)
+                .and_then(|column| row.get(*column))
+                .unwrap_or_default()
+        };
+        let row_context = || format!("Invalid row {}", row_number + 1);
+
+        let name = row.get(name_column).unwrap_or_default();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Row {} has no name", row_number + 1));
+        }
+
+        let frequency = match cell(CsvField::Frequency) {
+            "" => default_frequency,
+            freq => Frequency::from_str(freq).with_context(row_context)?,
+        };
+
+        snapshot.friends.push(FriendRow {
+            id: friend_id,
+            name: name.to_string(),
+            location: cell(CsvField::Location).to_string(),
+            frequency,
+            archived: false,
+            paused_until: None,
+        });
+
+        let last_seen = cell(CsvField::LastSeen);
+        if !last_seen.is_empty() {
+            let date = dates::parse_natural_date(last_seen, today).with_context(row_context)?;
+            snapshot.visits.push(Visit {
+                id: snapshot.visits.len() as i32 + 1,
+                friend_id,
+                date: date.to_string(),
+                created_at: Utc::now().format(CREATED_AT_FORMAT).to_string(),
+            });
+        }
+
+        for tag in cell(CsvField::Tags).split(TAG_SEPARATORS) {
+            if tag.trim().is_empty() {
+                continue;
+            }
+            let tag = names::normalize_tag(tag).with_context(row_context)?;
+            let tag_id = match snapshot.tags.iter().find(|t| t.name == tag) {
+                Some(existing) => existing.id,
+                None => {
+                    let tag_id = snapshot.tags.len() as i32 + 1;
+                    snapshot.tags.push(Tag {
+                        id: tag_id,
+                        name: tag,
+                    });
+                    tag_id
+                }
+            };
+            snapshot.friend_tags.push(FriendTag { friend_id, tag_id });
+        }
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 12, 8).unwrap()
+    }
+
+    #[test]
+    fn test_parse_column_mapping() {
+        let mapping =
+            parse_column_mapping(&["name=Full Name".to_string(), "tags = Groups".to_string()])
+                .unwrap();
+        assert_eq!(mapping[&CsvField::Name], "Full Name");
+        assert_eq!(mapping[&CsvField::Tags], "Groups");
+
+        assert!(parse_column_mapping(&["name".to_string()]).is_err());
+        assert!(parse_column_mapping(&["nickname=Nick".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_from_csv_default_columns() {
+        let text = "name,location,frequency,last_seen,tags\n\
+            Sam,The Shire,2m,2021-12-02,hobbits;college\n\
+            Gandolf,Middle Earth,,,college\n";
+        let snapshot = from_csv(text, &HashMap::new(), Frequency::weeks(10), today()).unwrap();
+
+        assert_eq!(snapshot.friends.len(), 2);
+        assert_eq!(snapshot.friends[0].frequency.to_string(), "2 months");
+        assert_eq!(snapshot.friends[1].frequency, Frequency::weeks(10));
+        assert_eq!(snapshot.visits.len(), 1);
+        assert_eq!(snapshot.visits[0].date, "2021-12-02");
+        assert_eq!(snapshot.tag_names(1), vec!["hobbits", "college"]);
+        assert_eq!(snapshot.tag_names(2), vec!["college"]);
+    }
+
+    #[test]
+    fn test_from_csv_mapped_columns() {
+        let text = "Full Name,City,Met\nFrodo,Hobbiton,yesterday\n";
+        let mapping = parse_column_mapping(&[
+            "name=full name".to_string(),
+            "location=City".to_string(),
+            "last_seen=Met".to_string(),
+        ])
+        .unwrap();
+        let snapshot = from_csv(text, &mapping, Frequency::weeks(4), today()).unwrap();
+
+        assert_eq!(snapshot.friends[0].name, "Frodo");
+        assert_eq!(snapshot.friends[0].location, "Hobbiton");
+        assert_eq!(snapshot.friends[0].frequency, Frequency::weeks(4));
+        assert_eq!(snapshot.visits[0].date, "2021-12-07");
+    }
+
+    #[test]
+    fn test_from_csv_errors() {
+        let no_name = "City\nHobbiton\n";
+        assert!(from_csv(no_name, &HashMap::new(), Frequency::weeks(10), today()).is_err());
+
+        let bad_freq = "name,freq_weeks\nSam,often\n";
+        assert!(from_csv(bad_freq, &HashMap::new(), Frequency::weeks(10), today()).is_err());
+
+        let bad_date = "name,last_seen\nSam,someday\n";
+        assert!(from_csv(bad_date, &HashMap::new(), Frequency::weeks(10), today()).is_err());
+    }
+
+    #[test]
+    fn test_restore_keeps_ids_free_after_inserts() {
+        let conn = db::connect(std::path::Path::new(":memory:")).unwrap();
+        let text = "name\nA\nB\n";
+        let snapshot = from_csv(text, &HashMap::new(), Frequency::weeks(10), today()).unwrap();
+        restore(&snapshot, MergeStrategy::Fail, false, &conn).unwrap();
+
+        // C can't keep id 1 so gets the next free id, 3, which D wants to keep
+        let mut snapshot = from_csv(
+            "name\nC\nD\n",
+            &HashMap::new(),
+            Frequency::weeks(10),
+            today(),
+        )
+        .unwrap();
+        snapshot.friends[0].id = 1;
+        snapshot.friends[1].id = 3;
+        restore(&snapshot, MergeStrategy::Fail, false, &conn).unwrap();
+
+        let names: Vec<String> = db::load_friend_rows(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(names.len(), 4);
+        assert!(names.contains(&"D".to_string()));
+    }
+
+    #[test]
+    fn test_restore_matches_names_ignoring_case() {
+        let conn = db::connect(std::path::Path::new(":memory:")).unwrap();
+        let snapshot = from_csv(
+            "name\nSam\n",
+            &HashMap::new(),
+            Frequency::weeks(10),
+            today(),
+        );
+        restore(&snapshot.unwrap(), MergeStrategy::Fail, false, &conn).unwrap();
+
+        let snapshot = from_csv(
+            "name,location\nsám,Bree\nFrodo,Bag End\nFRODO,Bree\n",
+            &HashMap::new(),
+            Frequency::weeks(10),
+            today(),
+        )
+        .unwrap();
+        assert!(restore(&snapshot, MergeStrategy::Fail, false, &conn).is_err());
+        let records = restore(&snapshot, MergeStrategy::Overwrite, false, &conn).unwrap();
+        let statuses: Vec<ImportStatus> = records.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ImportStatus::Overwritten,
+                ImportStatus::Added,
+                ImportStatus::Duplicate
+            ]
+        );
+
+        let rows = db::load_friend_rows(&conn).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].location, "Bree");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let text = "name,location,last_seen,tags\nSam,The Shire,2021-12-02,hobbits\n";
+        let snapshot = from_csv(text, &HashMap::new(), Frequency::weeks(10), today()).unwrap();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<Snapshot>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::frequency::Frequency;
+use crate::models::{Friend, Visit};
+use crate::output::{self, Record};
+use crate::upcoming::{DueDays, Urgency};
+use anyhow::Result;
+use chrono::NaiveDate;
+use prettytable::Row;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+/// How far back `stats` counts visits unless told otherwise, about three months.
+pub const DEFAULT_WINDOW_DAYS: i64 = 90;
+/// How many of the most overdue friends `stats` names.
+const MOST_NEGLECTED: usize = 5;
+const DAYS_PER_WEEK: f64 = 7.0;
+
+/// How many friends are seen this often.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FrequencyCount {
+    pub frequency: Frequency,
+    pub friends: usize,
+}
+
+/// An overdue friend, most urgent first.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NeglectedFriend {
+    pub name: String,
+    pub days_overdue: u16,
+    pub urgency: Urgency,
+}
+
+/// Whether you're keeping up with friends overall, with visits counted since a date.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Stats {
+    pub since: NaiveDate,
+    pub friends: usize,
+    pub by_frequency: Vec<FrequencyCount>,
+    pub overdue: usize,
+    pub overdue_percent: f64,
+    pub average_days_overdue: Option<f64>,
+    pub most_neglected: Vec<NeglectedFriend>,
+    pub never_seen: Vec<String>,
+    /// How many visits a week seeing everyone as often as you'd like takes
+    pub needed_visits_per_week: f64,
+    pub visits: usize,
+    pub friends_seen: usize,
+    pub visits_per_week: f64,
+}
+
+impl Stats {
+    pub fn new(
+        friends: &[Friend],
+        visits: &[Visit],
+        since: NaiveDate,
+        today: NaiveDate,
+    ) -> Result<Self> {
+        let mut by_frequency: Vec<FrequencyCount> = vec![];
+        let mut overdue: Vec<NeglectedFriend> = vec![];
+        let mut never_seen = vec![];
+        let mut needed_visits_per_week = 0.0;
+
+        for friend in friends {
+            match by_frequency
+                .iter_mut()
+                .find(|count| count.frequency == friend.frequency)
+            {
+                Some(count) => count.friends += 1,
+                None => by_frequency.push(FrequencyCount {
+                    frequency: friend.frequency,
+                    friends: 1,
+                }),
+            }
+            needed_visits_per_week += DAYS_PER_WEEK / friend.frequency.approx_days() as f64;
+
+            let due = friend.days_until_due(today)?;
+            match due {
+                DueDays::NotSeen => never_seen.push(friend.name.clone()),
+                DueDays::OverDue(days) => overdue.push(NeglectedFriend {
+                    name: friend.name.clone(),
+                    days_overdue: days,
+                    urgency: Urgency::new(&due, friend.frequency),
+                }),
+                DueDays::DueIn(_) => {}
+            }
+        }
+        by_frequency.sort_by_key(|count| count.frequency.approx_days());
+        never_seen.sort();
+        overdue.sort_by(|a, b| {
+            b.urgency
+                .cmp(&a.urgency)
+                .then_with(|| b.days_overdue.cmp(&a.days_overdue))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let friend_ids: HashSet<i32> = friends.iter().map(|f| f.id).collect();
+        let since_date = since.to_string();
+        let recent: Vec<&Visit> = visits
+            .iter()
+            .filter(|v| friend_ids.contains(&v.friend_id) && v.date >= since_date)
+            .collect();
+        let friends_seen = recent
+            .iter()
+            .map(|v| v.friend_id)
+            .collect::<HashSet<_>>()
+            .len();
+        let weeks = ((today - since).num_days() + 1) as f64 / DAYS_PER_WEEK;
+
+        let total_overdue_days: u32 = overdue.iter().map(|n| u32::from(n.days_overdue)).sum();
+        let average_days_overdue = match overdue.len() {
+            0 => None,
+            count => Some(round(f64::from(total_overdue_days) / count as f64)),
+        };
+        let overdue_percent = match friends.len() {
+            0 => 0.0,
+            count => round(overdue.len() as f64 * 100.0 / count as f64),
+        };
+
+        Ok(Self {
+            since,
+            friends: friends.len(),
+            by_frequency,
+            overdue: overdue.len(),
+            overdue_percent,
+            average_days_overdue,
+            most_neglected: overdue.into_iter().take(MOST_NEGLECTED).collect(),
+            never_seen,
+            needed_visits_per_week: round(needed_visits_per_week),
+            visits: recent.len(),
+            friends_seen,
+            visits_per_week: round(recent.len() as f64 / weeks),
+        })
+    }
+
+    fn frequencies(&self) -> Vec<String> {
+        self.by_frequency
+            .iter()
+            .map(|count| format!("{} {}", count.friends, count.frequency.every()))
+            .collect()
+    }
+
+    fn neglected(&self) -> Vec<String> {
+        self.most_neglected
+            .iter()
+            .map(|n| format!("{} ({} days)", n.name, n.days_overdue))
+            .collect()
+    }
+}
+
+// Round to what's displayed, so exported numbers match the report
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} friends", self.friends)?;
+        if !self.by_frequency.is_empty() {
+            write!(f, ": {}", self.frequencies().join(", "))?;
+        }
+        writeln!(f)?;
+        write!(f, "{} overdue ({:.0}%)", self.overdue, self.overdue_percent)?;
+        if let Some(average) = self.average_days_overdue {
+            write!(f, ", by {:.1} days on average", average)?;
+        }
+        writeln!(f)?;
+        if !self.most_neglected.is_empty() {
+            writeln!(f, "Most neglected: {}", self.neglected().join(", "))?;
+        }
+        if !self.never_seen.is_empty() {
+            writeln!(f, "Never seen: {}", self.never_seen.join(", "))?;
+        }
+        writeln!(
+            f,
+            "Keeping up takes {:.1} visits a week",
+            self.needed_visits_per_week
+        )?;
+        write!(
+            f,
+            "Since {}: {} visits with {} friends, {:.1} a week",
+            self.since, self.visits, self.friends_seen, self.visits_per_week
+        )
+    }
+}
+
+impl Record for Stats {
+    fn table_titles() -> Row {
+        row![
+            "Since",
+            "Friends",
+            "Overdue",
+            "Avg days overdue",
+            "Never seen",
+            "Visits a week needed",
+            "Visits a week"
+        ]
+    }
+
+    fn table_row(&self) -> Row {
+        row![
+            self.since,
+            self.friends,
+            format!("{} ({:.0}%)", self.overdue, self.overdue_percent),
+            output::optional_field(&self.average_days_overdue),
+            self.never_seen.len(),
+            self.needed_visits_per_week,
+            self.visits_per_week
+        ]
+    }
+
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "since",
+            "friends",
+            "by_frequency",
+            "overdue",
+            "overdue_percent",
+            "average_days_overdue",
+            "most_neglected",
+            "never_seen",
+            "needed_visits_per_week",
+            "visits",
+            "friends_seen",
+            "visits_per_week",
+        ]
+    }
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.since.to_string(),
+            self.friends.to_string(),
+            self.by_frequency
+                .iter()
+                .map(|count| format!("{}={}", count.frequency.short(), count.friends))
+                .collect::<Vec<_>>()
+                .join(";"),
+            self.overdue.to_string(),
+            self.overdue_percent.to_string(),
+            output::optional_field(&self.average_days_overdue),
+            self.most_neglected
+                .iter()
+                .map(|n| n.name.clone())
+                .collect::<Vec<_>>()
+                .join(";"),
+            self.never_seen.join(";"),
+            self.needed_visits_per_week.to_string(),
+            self.visits.to_string(),
+            self.friends_seen.to_string(),
+            self.visits_per_week.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::date;
+
+    #[test]
+    fn test_stats() {
+        let friends = vec![
+            Friend::named(1, "Sam")
+                .every(Frequency::weeks(1))
+                .seen("2021-12-01"),
+            Friend::named(2, "Frodo")
+                .every(Frequency::weeks(1))
+                .seen("2021-11-01"),
+            Friend::named(3, "Gandolf")
+                .every(Frequency::weeks(7))
+                .seen("2021-10-01"),
+            Friend::named(4, "Pip").every(Frequency::weeks(7)),
+        ];
+        let visits = vec![
+            Visit::on(1, 1, "2021-12-01"),
+            Visit::on(2, 1, "2021-11-24"),
+            Visit::on(3, 2, "2021-11-01"),
+            Visit::on(4, 3, "2021-10-01"),
+            Visit::on(5, 9, "2021-12-02"),
+        ];
+        let stats = Stats::new(&friends, &visits, date("2021-11-11"), date("2021-12-08")).unwrap();
+
+        assert_eq!(
+            stats.by_frequency,
+            vec![
+                FrequencyCount {
+                    frequency: Frequency::weeks(1),
+                    friends: 2,
+                },
+                FrequencyCount {
+                    frequency: Frequency::weeks(7),
+                    friends: 2,
+                },
+            ]
+        );
+        assert_eq!(stats.overdue, 2);
+        assert_eq!(stats.overdue_percent, 50.0);
+        // Frodo is 30 days overdue and Gandolf 19 days
+        assert_eq!(stats.average_days_overdue, Some(24.5));
+        let neglected: Vec<&str> = stats
+            .most_neglected
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(neglected, vec!["Frodo", "Gandolf"]);
+        assert_eq!(stats.never_seen, vec!["Pip"]);
+        assert_eq!(stats.needed_visits_per_week, 2.29);
+        assert_eq!(stats.visits, 2);
+        assert_eq!(stats.friends_seen, 1);
+        assert_eq!(stats.visits_per_week, 0.5);
+    }
+
+    #[test]
+    fn test_stats_no_friends() {
+        let stats = Stats::new(&[], &[], date("2021-12-08"), date("2021-12-08")).unwrap();
+        assert_eq!(stats.overdue_percent, 0.0);
+        assert_eq!(stats.average_days_overdue, None);
+        assert_eq!(stats.visits_per_week, 0.0);
+        assert!(stats.to_string().starts_with("0 friends\n0 overdue (0%)\n"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::models::{AwayPeriod, Friend, FriendRecord};
+use crate::output::{self, Record};
+use crate::upcoming::{DueDisplay, UpcomingRecord, Urgency};
+use crate::{dates, names};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use prettytable::{Cell, Row};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+
+/// How many friends `trip` plans to see each day unless told otherwise.
+pub const DEFAULT_PER_DAY: usize = 2;
+/// The longest trip `trip` will plan, so a typo in a year doesn't plan for decades.
+pub const MAX_TRIP_DAYS: i64 = 366;
+
+/// Every way of writing a place: as given, what it's an alias for, and its other aliases,
+/// all normalized.
+pub fn place_names(location: &str, aliases: &BTreeMap<String, String>) -> Vec<String> {
+    let wanted = names::normalize(location);
+    let place = aliases
+        .iter()
+        .find(|(alias, _)| names::normalize(alias) == wanted)
+        .map(|(_, place)| names::normalize(place))
+        .unwrap_or_else(|| wanted.clone());
+
+    let mut place_names = vec![wanted, place.clone()];
+    place_names.extend(
+        aliases
+            .iter()
+            .filter(|(_, other)| names::normalize(other) == place)
+            .map(|(alias, _)| names::normalize(alias)),
+    );
+    place_names.sort();
+    place_names.dedup();
+    place_names
+}
+
+/// Whether a friend's location has any of the place names as whole words, so `NYC`
+/// doesn't match "Sunnyvale".
+pub fn in_place(location: &str, place_names: &[String]) -> bool {
+    let location = names::normalize(location);
+    let location = words(&location);
+    place_names.iter().any(|place| {
+        let place = words(place);
+        !place.is_empty() && location.windows(place.len()).any(|w| w == place.as_slice())
+    })
+}
+
+fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Where you are while away, for `upcoming` to list only friends there.
+#[derive(Clone, Debug)]
+pub struct Away {
+    pub location: String,
+    pub until: NaiveDate,
+    place_names: Vec<String>,
+    /// Days until heading home, so friends there coming due before then are listed
+    pub days_left: u16,
+}
+
+impl Away {
+    pub fn new(
+        period: &AwayPeriod,
+        aliases: &BTreeMap<String, String>,
+        today: NaiveDate,
+    ) -> Result<Self> {
+        let until = dates::parse_date(&period.end_date)?;
+        Ok(Self {
+            location: period.location.clone(),
+            until,
+            place_names: place_names(&period.location, aliases),
+            days_left: (until - today).num_days().try_into().unwrap_or(u16::MAX),
+        })
+    }
+
+    pub fn is_nearby(&self, friend: &Friend) -> bool {
+        in_place(&friend.location, &self.place_names)
+    }
+}
+
+/// A friend to see on a trip, on a day of it or, when the days are full, if there's time.
+#[derive(Serialize)]
+pub struct TripStop {
+    pub day: Option<NaiveDate>,
+    #[serde(flatten)]
+    pub stop: UpcomingRecord,
+}
+
+/// Spread friends over the days from `from` to `to`, most urgent by the last day first.
+/// Each goes on the day they come due, or the first day when already due, moving later
+/// and then earlier when that day already has `per_day` friends. How soon they're due is
+/// shown as of the first day, like their urgency is for the last.
+pub fn plan(
+    friends: Vec<Friend>,
+    from: NaiveDate,
+    to: NaiveDate,
+    per_day: usize,
+    display: &DueDisplay,
+) -> Result<Vec<TripStop>> {
+    let mut ranked = friends
+        .into_iter()
+        .map(|friend| {
+            let due = friend.days_until_due(to)?;
+            let urgency = Urgency::new(&due, friend.frequency);
+            let wanted = friend
+                .due_date()?
+                .map_or(from, |due_date| due_date.max(from).min(to));
+            Ok((urgency, due, wanted, friend))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ranked.sort_by(
+        |(urgency, due, _, friend), (other_urgency, other_due, _, other_friend)| {
+            other_urgency
+                .cmp(urgency)
+                .then_with(|| other_due.cmp(due))
+                .then_with(|| friend.name.cmp(&other_friend.name))
+        },
+    );
+
+    let days: Vec<NaiveDate> = (0..=(to - from).num_days())
+        .map(|offset| from + Duration::days(offset))
+        .collect();
+    let mut booked = vec![0; days.len()];
+    let mut stops = ranked
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (urgency, _, wanted, friend))| {
+            let wanted = days.iter().position(|day| *day == wanted).unwrap_or(0);
+            let slot = (wanted..days.len())
+                .chain((0..wanted).rev())
+                .find(|&i| booked[i] < per_day);
+            if let Some(i) = slot {
+                booked[i] += 1;
+            }
+            Ok((
+                rank,
+                TripStop {
+                    day: slot.map(|i| days[i]),
+                    stop: UpcomingRecord {
+                        record: FriendRecord::new(friend, from, display)?,
+                        urgency,
+                    },
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Days in order with spare friends last, most urgent first within each
+    stops.sort_by_key(|(rank, stop)| (stop.day.is_none(), stop.day, *rank));
+    Ok(stops.into_iter().map(|(_, stop)| stop).collect())
+}
+
+impl TripStop {
+    fn day_cell(&self) -> String {
+        match self.day {
+            Some(day) => self.stop.record.display.date(day),
+            None => "If time".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TripStop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.day {
+            Some(day) => write!(f, "On {}, see ", self.stop.record.display.date(day))?,
+            None => write!(f, "If there's time, see ")?,
+        }
+        self.stop.fmt(f)
+    }
+}
+
+impl Record for TripStop {
+    fn table_titles() -> Row {
+        Self::table_titles_with(&DueDisplay::default())
+    }
+
+    fn table_titles_with(display: &DueDisplay) -> Row {
+        let mut r = UpcomingRecord::table_titles_with(display);
+        r.insert_cell(0, Cell::new("Day"));
+        r
+    }
+
+    fn table_row(&self) -> Row {
+        let mut r = self.stop.table_row();
+        r.insert_cell(0, Cell::new(&self.day_cell()));
+        r
+    }
+
+    fn headers() -> Vec<&'static str> {
+        let mut headers = vec!["day"];
+        headers.extend(UpcomingRecord::headers());
+        headers
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = vec![output::optional_field(&self.day)];
+        fields.extend(self.stop.fields());
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::date;
+    use crate::upcoming::DueDays;
+
+    #[test]
+    fn test_place_names() {
+        let aliases: BTreeMap<String, String> = vec![
+            ("NYC".to_string(), "New York".to_string()),
+            ("Big Apple".to_string(), "new york".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let nyc = place_names("nyc", &aliases);
+        assert_eq!(nyc, vec!["big apple", "new york", "nyc"]);
+        assert_eq!(place_names("New York", &aliases), nyc);
+        assert_eq!(place_names("Bree", &aliases), vec!["bree"]);
+
+        assert!(in_place("Brooklyn, New York", &nyc));
+        assert!(in_place("NYC", &nyc));
+        assert!(!in_place("Sunnyvale", &nyc));
+        assert!(!in_place("York", &nyc));
+        assert!(in_place("Montréal", &place_names("montreal", &aliases)));
+    }
+
+    #[test]
+    fn test_plan() {
+        let friends = vec![
+            Friend::named(1, "Due later")
+                .located("Bree")
+                .seen("2021-11-25"),
+            Friend::named(2, "Not seen").located("Bree"),
+            Friend::named(3, "Overdue")
+                .located("Bree")
+                .seen("2021-11-01"),
+            Friend::named(4, "Also overdue")
+                .located("Bree")
+                .seen("2021-11-10"),
+            Friend::named(5, "Not due")
+                .located("Bree")
+                .seen("2021-12-01"),
+        ];
+        let stops = plan(
+            friends,
+            date("2021-12-06"),
+            date("2021-12-08"),
+            2,
+            &DueDisplay::default(),
+        )
+        .unwrap();
+        let planned: Vec<(Option<NaiveDate>, &str)> = stops
+            .iter()
+            .map(|s| (s.day, s.stop.record.friend.name.as_str()))
+            .collect();
+        assert_eq!(
+            planned,
+            vec![
+                (Some(date("2021-12-06")), "Not seen"),
+                (Some(date("2021-12-06")), "Overdue"),
+                (Some(date("2021-12-07")), "Also overdue"),
+                (Some(date("2021-12-08")), "Due later"),
+                (Some(date("2021-12-08")), "Not due"),
+            ]
+        );
+        assert!(stops[2].to_string().starts_with(
+            "On 12/07, see Also overdue (Bree) every 2 weeks, last seen on 2021-11-10"
+        ));
+        // Shown as due in 3 days from the first day, rather than 1 from the last
+        assert_eq!(stops[3].stop.record.due, DueDays::DueIn(3));
+
+        let stops = plan(
+            vec![
+                Friend::named(1, "Sam").located("Bree"),
+                Friend::named(2, "Rosie").located("Bree"),
+            ],
+            date("2021-12-06"),
+            date("2021-12-06"),
+            1,
+            &DueDisplay::default(),
+        )
+        .unwrap();
+        assert_eq!(stops[0].day, Some(date("2021-12-06")));
+        assert_eq!(stops[1].day, None);
+        assert!(stops[1].to_string().starts_with("If there's time, see"));
+    }
+}
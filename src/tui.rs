@@ -0,0 +1,441 @@
+use crate::db::{self, SqliteConnection};
+use crate::frequency::Frequency;
+use crate::models::*;
+use crate::upcoming::{DueDisplay, UpcomingFriends, UpcomingOptions, UpcomingRecord};
+use crate::{dates, journal, names};
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::iter;
+
+const RECENT_VISITS: usize = 10;
+const HELP: &str = "↑/↓ move  s seen today  f frequency  l location  / filter  r reload  q quit";
+
+enum Mode {
+    Browse,
+    EditFrequency(String),
+    EditLocation(String),
+    Filter(String),
+}
+
+struct App<'a> {
+    conn: &'a SqliteConnection,
+    options: UpcomingOptions,
+    due_display: DueDisplay,
+    upcoming: Vec<UpcomingRecord>,
+    visits: Vec<Visit>,
+    table_state: TableState,
+    filter: String,
+    mode: Mode,
+    status: String,
+    quit: bool,
+}
+
+pub fn run(
+    options: UpcomingOptions,
+    due_display: DueDisplay,
+    conn: &SqliteConnection,
+) -> Result<()> {
+    let mut app = App::new(options, due_display, conn)?;
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl<'a> App<'a> {
+    fn new(
+        options: UpcomingOptions,
+        due_display: DueDisplay,
+        conn: &'a SqliteConnection,
+    ) -> Result<Self> {
+        let mut app = Self {
+            conn,
+            options,
+            due_display,
+            upcoming: vec![],
+            visits: vec![],
+            table_state: TableState::default(),
+            filter: String::new(),
+            mode: Mode::Browse,
+            status: String::new(),
+            quit: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    if let Err(e) = self.handle_key(key.code) {
+                        self.status = format!("Error: {:#}", e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn selected_record(&self) -> Option<&UpcomingRecord> {
+        self.table_state
+            .selected()
+            .and_then(|i| self.upcoming.get(i))
+    }
+
+    fn selected(&self) -> Option<&Friend> {
+        self.selected_record().map(|record| &record.record.friend)
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let selected_id = self.selected().map(|f| f.id);
+        let filter = names::normalize(&self.filter);
+        let today = dates::local_today();
+
+        let mut upcoming_friends = UpcomingFriends::new(self.options.clone());
+        let friends =
+            db::load_all_friends(None, Some(today), self.conn).context("Failed to load friends")?;
+        for friend in friends {
+            let matches = iter::once(&friend.name)
+                .chain(iter::once(&friend.location))
+                .chain(friend.tags.iter())
+                .any(|field| names::normalize(field).contains(&filter));
+            if matches {
+                upcoming_friends.push(friend, today)?;
+            }
+        }
+        self.upcoming = upcoming_friends.into_records(today, &self.due_display)?;
+
+        let position = selected_id
+            .and_then(|id| self.upcoming.iter().position(|r| r.record.friend.id == id))
+            .or_else(|| (!self.upcoming.is_empty()).then_some(0));
+        self.table_state.select(position);
+        self.load_visits()
+    }
+
+    fn load_visits(&mut self) -> Result<()> {
+        self.visits = match self.selected() {
+            Some(friend) => db::load_visits(friend, self.conn).context("Failed to load visits")?,
+            None => vec![],
+        };
+        Ok(())
+    }
+
+    fn move_selection(&mut self, forward: bool) -> Result<()> {
+        if self.upcoming.is_empty() {
+            return Ok(());
+        }
+        let last = self.upcoming.len() - 1;
+        let selected = self.table_state.selected().unwrap_or(0);
+        let next = match forward {
+            true if selected < last => selected + 1,
+            false if selected > 0 => selected - 1,
+            _ => selected,
+        };
+        self.table_state.select(Some(next));
+        self.load_visits()
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> Result<()> {
+        let mode = std::mem::replace(&mut self.mode, Mode::Browse);
+        match mode {
+            Mode::Browse => self.handle_browse_key(code),
+            Mode::EditFrequency(input) => self.handle_input(code, input, Mode::EditFrequency),
+            Mode::EditLocation(input) => self.handle_input(code, input, Mode::EditLocation),
+            Mode::Filter(input) => self.handle_input(code, input, Mode::Filter),
+        }
+    }
+
+    fn handle_browse_key(&mut self, code: KeyCode) -> Result<()> {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true)?,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false)?,
+            KeyCode::Char('r') => {
+                self.reload()?;
+                self.status = "Reloaded".to_string();
+            }
+            KeyCode::Char('s') => self.record_seen_today()?,
+            KeyCode::Char('f') => {
+                if let Some(friend) = self.selected() {
+                    self.mode = Mode::EditFrequency(friend.frequency.short());
+                }
+            }
+            KeyCode::Char('l') => {
+                if let Some(friend) = self.selected() {
+                    self.mode = Mode::EditLocation(friend.location.clone());
+                }
+            }
+            KeyCode::Char('/') => self.mode = Mode::Filter(self.filter.clone()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_input(
+        &mut self,
+        code: KeyCode,
+        mut input: String,
+        mode: fn(String) -> Mode,
+    ) -> Result<()> {
+        match code {
+            KeyCode::Esc => self.status.clear(),
+            KeyCode::Enter => {
+                let submitted = mode(input);
+                self.submit(submitted)?;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                self.mode = mode(input);
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                self.mode = mode(input);
+            }
+            _ => self.mode = mode(input),
+        }
+        Ok(())
+    }
+
+    fn submit(&mut self, submitted: Mode) -> Result<()> {
+        match submitted {
+            Mode::Filter(filter) => {
+                self.filter = filter.trim().to_string();
+                self.status.clear();
+            }
+            Mode::EditFrequency(input) => {
+                let frequency: Frequency = input.parse()?;
+                if let Some(friend) = self.selected().cloned() {
+                    let command = format!("tui set-freq {} {}", friend.name, frequency.short());
+                    journal::track(&command, self.conn, || {
+                        db::update_frequency(&friend, frequency, self.conn)
+                            .context("Failed to set frequency")
+                    })?;
+                    self.status = format!("Set {} to {}", friend.name, frequency.every());
+                }
+            }
+            Mode::EditLocation(input) => {
+                if let Some(friend) = self.selected().cloned() {
+                    let location = input.trim().to_string();
+                    let command = format!("tui set-loc {} {}", friend.name, location);
+                    journal::track(&command, self.conn, || {
+                        db::update_location(&friend, location.clone(), self.conn)
+                            .context("Failed to set location")
+                    })?;
+                    self.status = format!("Moved {} to {}", friend.name, location);
+                }
+            }
+            Mode::Browse => {}
+        }
+        self.reload()
+    }
+
+    fn record_seen_today(&mut self) -> Result<()> {
+        let friend = match self.selected().cloned() {
+            Some(friend) => friend,
+            None => return Ok(()),
+        };
+        let today = dates::local_today();
+        dates::check_new_seen(today, friend.last_seen.clone(), false)?;
+
+        let today = today.to_string();
+        if db::visit_exists(&friend, &today, self.conn).context("Failed to load visits")? {
+            self.status = format!("Already recorded seeing {} today", friend.name);
+        } else {
+            let command = format!("tui record {} {}", friend.name, today);
+            journal::track(&command, self.conn, || {
+                db::insert_visit(&friend, today.clone(), self.conn).context("Failed to record seen")
+            })?;
+            self.status = format!("Recorded seeing {} today", friend.name);
+        }
+        self.reload()
+    }
+
+    fn queue_cells(&self, record: &UpcomingRecord) -> Vec<String> {
+        let friend = &record.record.friend;
+        let due = self
+            .due_display
+            .describe(&record.record.due, record.record.due_date);
+        vec![
+            friend.name.clone(),
+            friend.location.clone(),
+            due.unwrap_or_default(),
+        ]
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(frame.area());
+        let [queue, detail] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(main);
+
+        let rows = self
+            .upcoming
+            .iter()
+            .map(|record| Row::new(self.queue_cells(record)));
+        let title = match self.filter.as_str() {
+            "" => "Upcoming".to_string(),
+            filter => format!("Upcoming matching '{}'", filter),
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(40),
+                Constraint::Percentage(35),
+                Constraint::Percentage(25),
+            ],
+        )
+        .header(Row::new(vec!["Name", "Location", "Due"]).style(Style::new().bold()))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(title));
+        frame.render_stateful_widget(table, queue, &mut self.table_state);
+
+        let mut lines: Vec<Line> = vec![];
+        if let Some(record) = self.selected_record() {
+            lines.push(Line::from(record.to_string()));
+            lines.push(Line::from(""));
+            lines.push(Line::from("Recent visits").bold());
+            if self.visits.is_empty() {
+                lines.push(Line::from("None yet"));
+            }
+            for visit in self.visits.iter().take(RECENT_VISITS) {
+                lines.push(Line::from(visit.date.clone()));
+            }
+        }
+        let details = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title("Friend"));
+        frame.render_widget(details, detail);
+
+        let prompt = match &self.mode {
+            Mode::Browse => self.status.clone(),
+            Mode::EditFrequency(input) => format!("How often, like 4d, 3w, 2m or 1y: {}", input),
+            Mode::EditLocation(input) => format!("Location: {}", input),
+            Mode::Filter(input) => format!("Filter by name, location or tag: {}", input),
+        };
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(prompt), Line::from(HELP)]),
+            footer,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upcoming::{DueStyle, Urgency};
+    use chrono::NaiveDate;
+
+    fn add_friend(name: &str, location: &str, conn: &SqliteConnection) {
+        let new_friend = NewFriend {
+            name: name.to_string(),
+            location: location.to_string(),
+            frequency: Frequency::weeks(2),
+        };
+        db::insert_friend(new_friend, conn).unwrap();
+    }
+
+    fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(KeyCode::Char(c)).unwrap();
+        }
+    }
+
+    fn listed<'a>(app: &'a App) -> Vec<&'a str> {
+        let mut names: Vec<&str> = app
+            .upcoming
+            .iter()
+            .map(|r| r.record.friend.name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_filter() {
+        let conn = db::connect(std::path::Path::new(":memory:")).unwrap();
+        add_friend("Sam", "Bag End", &conn);
+        add_friend("Frodo", "Bag End", &conn);
+        add_friend("Éowyn", "Edoras", &conn);
+        db::insert_tag(1, "gardeners", &conn).unwrap();
+        let mut app = App::new(UpcomingOptions::default(), DueDisplay::default(), &conn).unwrap();
+        assert_eq!(listed(&app), vec!["Frodo", "Sam", "Éowyn"]);
+
+        type_keys(&mut app, "/bag end");
+        app.handle_key(KeyCode::Enter).unwrap();
+        assert_eq!(app.filter, "bag end");
+        assert_eq!(listed(&app), vec!["Frodo", "Sam"]);
+
+        // Tags match too, and accents are ignored
+        for (filter, expected) in [("GARDEN", vec!["Sam"]), ("eowyn", vec!["Éowyn"])] {
+            app.filter = filter.to_string();
+            app.reload().unwrap();
+            assert_eq!(listed(&app), expected);
+        }
+
+        // Escape leaves the filter as it was
+        type_keys(&mut app, "/x");
+        app.handle_key(KeyCode::Esc).unwrap();
+        assert_eq!(app.filter, "eowyn");
+        assert!(matches!(app.mode, Mode::Browse));
+    }
+
+    #[test]
+    fn test_edit_frequency() {
+        let conn = db::connect(std::path::Path::new(":memory:")).unwrap();
+        add_friend("Sam", "Bag End", &conn);
+        let mut app = App::new(UpcomingOptions::default(), DueDisplay::default(), &conn).unwrap();
+
+        type_keys(&mut app, "f");
+        assert!(matches!(&app.mode, Mode::EditFrequency(input) if input == "2w"));
+        app.handle_key(KeyCode::Backspace).unwrap();
+        app.handle_key(KeyCode::Backspace).unwrap();
+        type_keys(&mut app, "often");
+        assert!(app.handle_key(KeyCode::Enter).is_err());
+        assert_eq!(
+            db::load_friend("Sam", &conn).unwrap().frequency,
+            Frequency::weeks(2)
+        );
+
+        type_keys(&mut app, "f");
+        app.handle_key(KeyCode::Backspace).unwrap();
+        app.handle_key(KeyCode::Backspace).unwrap();
+        type_keys(&mut app, "3w");
+        app.handle_key(KeyCode::Enter).unwrap();
+        assert_eq!(
+            db::load_friend("Sam", &conn).unwrap().frequency,
+            Frequency::weeks(3)
+        );
+        assert_eq!(app.status, "Set Sam to every 3 weeks");
+        assert_eq!(app.selected().unwrap().frequency, Frequency::weeks(3));
+    }
+
+    #[test]
+    fn test_queue_cells() {
+        let conn = db::connect(std::path::Path::new(":memory:")).unwrap();
+        let display = DueDisplay::new(DueStyle::Absolute, "%d.%m.").unwrap();
+        let mut app = App::new(UpcomingOptions::default(), display, &conn).unwrap();
+        let today = NaiveDate::from_ymd_opt(2021, 12, 6).unwrap();
+        let friend = Friend::named(1, "Sam").seen("2021-12-01");
+        let record = FriendRecord::new(friend, today, &app.due_display).unwrap();
+        let urgency = Urgency::new(&record.due, record.friend.frequency);
+        let record = UpcomingRecord { record, urgency };
+        assert_eq!(
+            app.queue_cells(&record),
+            vec!["Sam", "The Shire", "on 15.12."]
+        );
+
+        app.due_display = DueDisplay::new(DueStyle::Relative, "%d.%m.").unwrap();
+        assert_eq!(
+            app.queue_cells(&record),
+            vec!["Sam", "The Shire", "in 9 days"]
+        );
+    }
+}
@@ -1,20 +1,57 @@
+use crate::frequency::Frequency;
+use crate::important_dates::DateRecord;
 use crate::models::*;
+use crate::names;
+use crate::output::{self, Record};
+use crate::trip::Away;
 use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
 use chrono::NaiveDate;
-use prettytable::{format, Table};
+use prettytable::{Cell, Row};
 use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize, Serializer};
 use std::cmp::Ordering;
+use std::fmt;
+use strum::{Display, EnumString, EnumVariantNames, IntoStaticStr};
 
-const UP_SOON_CUTOFF_DAYS: u16 = 10;
+pub const UP_SOON_CUTOFF_DAYS: u16 = 10;
+pub const DEFAULT_DUE_DATE_FORMAT: &str = "%m/%d";
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Display, EnumString, EnumVariantNames, IntoStaticStr,
+)]
+pub enum UpcomingSort {
+    #[strum(serialize = "urgency")]
+    Urgency,
+    #[strum(serialize = "absolute")]
+    Absolute,
+}
+
+#[derive(Serialize, Eq, PartialEq, Clone, Debug)]
+#[serde(tag = "status", content = "days", rename_all = "snake_case")]
 pub enum DueDays {
     NotSeen,
+    #[serde(rename = "overdue")]
     OverDue(u16),
     DueIn(u16),
 }
 
 impl DueDays {
+    pub fn status(&self) -> &'static str {
+        match self {
+            DueDays::NotSeen => "not_seen",
+            DueDays::OverDue(_) => "overdue",
+            DueDays::DueIn(_) => "due_in",
+        }
+    }
+
+    pub fn days(&self) -> Option<u16> {
+        match self {
+            DueDays::NotSeen => None,
+            DueDays::OverDue(d) | DueDays::DueIn(d) => Some(*d),
+        }
+    }
+
     pub fn display_some(&self) -> Option<String> {
         match self {
             DueDays::DueIn(d) => Some(format!("in {} days", d)),
@@ -24,6 +61,96 @@ impl DueDays {
     }
 }
 
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Display,
+    EnumString,
+    EnumVariantNames,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DueStyle {
+    #[strum(serialize = "relative")]
+    Relative,
+    #[strum(serialize = "absolute")]
+    Absolute,
+    #[strum(serialize = "both")]
+    Both,
+}
+
+/// How to describe when a friend is due, like "on 12/08" or "in 6 days".
+#[derive(Clone, Debug, PartialEq)]
+pub struct DueDisplay {
+    style: DueStyle,
+    date_format: String,
+}
+
+impl Default for DueDisplay {
+    fn default() -> Self {
+        Self {
+            style: DueStyle::Absolute,
+            date_format: DEFAULT_DUE_DATE_FORMAT.to_string(),
+        }
+    }
+}
+
+impl DueDisplay {
+    pub fn new(style: DueStyle, date_format: &str) -> Result<Self> {
+        if StrftimeItems::new(date_format).any(|item| matches!(item, Item::Error)) {
+            return Err(anyhow::anyhow!("Invalid date format '{}'", date_format));
+        }
+        Ok(Self {
+            style,
+            date_format: date_format.to_string(),
+        })
+    }
+
+    pub fn date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    pub fn style(&self) -> DueStyle {
+        self.style
+    }
+
+    /// Titles of the due columns in tables: the date, how long until then, or both.
+    pub fn titles(&self) -> Vec<&'static str> {
+        match self.style {
+            DueStyle::Relative => vec!["Due"],
+            DueStyle::Absolute => vec!["Due date"],
+            DueStyle::Both => vec!["Due date", "Due"],
+        }
+    }
+
+    /// Cells for the columns named by `titles`.
+    pub fn cells(&self, date: String, relative: String) -> Vec<String> {
+        match self.style {
+            DueStyle::Relative => vec![relative],
+            DueStyle::Absolute => vec![date],
+            DueStyle::Both => vec![date, relative],
+        }
+    }
+
+    pub fn describe(&self, due: &DueDays, due_date: Option<NaiveDate>) -> Option<String> {
+        let relative = due.display_some();
+        let absolute = due_date.map(|date| format!("on {}", self.date(date)));
+        match self.style {
+            DueStyle::Relative => relative,
+            DueStyle::Absolute => absolute,
+            DueStyle::Both => match (absolute, relative) {
+                (Some(absolute), Some(relative)) => Some(format!("{} ({})", absolute, relative)),
+                (absolute, relative) => absolute.or(relative),
+            },
+        }
+    }
+}
+
 impl Ord for DueDays {
     fn cmp(&self, other: &Self) -> Ordering {
         use DueDays::*;
@@ -53,39 +180,375 @@ impl PartialOrd for DueDays {
     }
 }
 
+/// How overdue a friend is as a fraction of how often to see them, so 0 is due today,
+/// 0.5 is half a period late and negative scores are not due yet.
+#[derive(Clone, Copy, Debug)]
+pub enum Urgency {
+    NotSeen,
+    Score(f64),
+}
+
+impl Urgency {
+    pub fn new(due: &DueDays, frequency: Frequency) -> Self {
+        let freq_days = frequency.approx_days().max(1) as f64;
+        match due {
+            DueDays::NotSeen => Urgency::NotSeen,
+            DueDays::OverDue(d) => Urgency::Score(f64::from(*d) / freq_days),
+            DueDays::DueIn(d) => Urgency::Score(-f64::from(*d) / freq_days),
+        }
+    }
+
+    pub fn score(&self) -> Option<f64> {
+        match self {
+            Urgency::NotSeen => None,
+            // Round to what's displayed, so the exported score matches the table
+            Urgency::Score(score) => Some((score * 100.0).round() / 100.0),
+        }
+    }
+}
+
+impl fmt::Display for Urgency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Urgency::NotSeen => Ok(()),
+            Urgency::Score(score) => write!(f, "{:.2}", score),
+        }
+    }
+}
+
+impl Serialize for Urgency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.score().serialize(serializer)
+    }
+}
+
+impl Ord for Urgency {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Urgency::NotSeen, Urgency::NotSeen) => Ordering::Equal,
+            (Urgency::NotSeen, _) => Ordering::Greater,
+            (_, Urgency::NotSeen) => Ordering::Less,
+            (Urgency::Score(score), Urgency::Score(other_score)) => score.total_cmp(other_score),
+        }
+    }
+}
+
+impl PartialOrd for Urgency {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Urgency {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Urgency {}
+
+#[derive(Clone, Debug)]
+struct Priority {
+    sort: UpcomingSort,
+    due: DueDays,
+    urgency: Urgency,
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.sort {
+            UpcomingSort::Absolute => self.due.cmp(&other.due),
+            UpcomingSort::Urgency => self
+                .urgency
+                .cmp(&other.urgency)
+                .then_with(|| self.due.cmp(&other.due)),
+        }
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Priority {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Priority {}
+
+#[derive(Clone, Debug)]
+pub struct UpcomingOptions {
+    pub sort: UpcomingSort,
+    /// How many days ahead to look for friends coming due
+    pub days: u16,
+    pub overdue_only: bool,
+    pub include_unseen: bool,
+    /// Only friends whose location contains this, ignoring case and accents
+    pub location: Option<String>,
+    pub limit: Option<usize>,
+    /// Where you're away, so only friends there are listed, up to when you head home
+    pub away: Option<Away>,
+}
+
+impl Default for UpcomingOptions {
+    fn default() -> Self {
+        Self {
+            sort: UpcomingSort::Urgency,
+            days: UP_SOON_CUTOFF_DAYS,
+            overdue_only: false,
+            include_unseen: true,
+            location: None,
+            limit: None,
+            away: None,
+        }
+    }
+}
+
+impl UpcomingOptions {
+    fn includes(&self, friend: &Friend, due: &DueDays) -> bool {
+        let days = match &self.away {
+            Some(away) if !away.is_nearby(friend) => return false,
+            Some(away) => self.days.max(away.days_left),
+            None => self.days,
+        };
+        let in_window = match due {
+            DueDays::NotSeen => self.include_unseen,
+            DueDays::OverDue(_) => true,
+            DueDays::DueIn(due_in) => !self.overdue_only && *due_in <= days,
+        };
+        in_window && self.in_location(friend)
+    }
+
+    /// Important dates are never overdue, so they only show when looking ahead.
+    fn includes_date(&self, friend: &Friend, days_until: i64) -> bool {
+        let nearby = self.away.as_ref().is_none_or(|away| away.is_nearby(friend));
+        nearby
+            && !self.overdue_only
+            && days_until <= i64::from(self.days)
+            && self.in_location(friend)
+    }
+
+    fn in_location(&self, friend: &Friend) -> bool {
+        self.location.as_ref().is_none_or(|location| {
+            names::normalize(&friend.location).contains(&names::normalize(location))
+        })
+    }
+}
+
 pub struct UpcomingFriends {
-    queue_by_due_days: PriorityQueue<Friend, DueDays>,
+    options: UpcomingOptions,
+    queue: PriorityQueue<Friend, Priority>,
+    dates: Vec<(NaiveDate, Friend, ImportantDate)>,
 }
 
 impl UpcomingFriends {
-    pub fn new() -> Self {
+    pub fn new(options: UpcomingOptions) -> Self {
         Self {
-            queue_by_due_days: PriorityQueue::new(),
+            options,
+            queue: PriorityQueue::new(),
+            dates: vec![],
         }
     }
 
-    pub fn push(&mut self, friend: Friend, today: NaiveDate) -> Result<()> {
-        friend.days_until_due(today).map(|due_days| match due_days {
-            DueDays::DueIn(days) if days > UP_SOON_CUTOFF_DAYS => {
-                // Don't track this far into the future
+    pub fn push_date(&mut self, friend: &Friend, date: ImportantDate, today: NaiveDate) {
+        if let Some(next) = date.next_on(today) {
+            if self
+                .options
+                .includes_date(friend, (next - today).num_days())
+            {
+                self.dates.push((next, friend.clone(), date));
             }
-            due_days => {
-                self.queue_by_due_days.push(friend, due_days);
+        }
+    }
+
+    /// Important dates coming up, soonest first, then friends coming due, with the limit
+    /// counting both.
+    pub fn into_rows(mut self, today: NaiveDate, display: &DueDisplay) -> Result<Vec<UpcomingRow>> {
+        let limit = self.options.limit.unwrap_or(usize::MAX);
+        let mut dates = std::mem::take(&mut self.dates);
+        dates.sort_by(|(next, friend, _), (other_next, other_friend, _)| {
+            next.cmp(other_next)
+                .then_with(|| friend.name.cmp(&other_friend.name))
+        });
+        let mut rows = dates
+            .into_iter()
+            .take(limit)
+            .map(|(_, friend, date)| {
+                Ok(UpcomingRow::Date(UpcomingDateRecord {
+                    date: DateRecord::new(&date, today, display)?,
+                    record: FriendRecord::new(friend, today, display)?,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.options.limit = Some(limit - rows.len());
+        rows.extend(
+            self.into_records(today, display)?
+                .into_iter()
+                .map(UpcomingRow::Due),
+        );
+        Ok(rows)
+    }
+
+    pub fn push(&mut self, friend: Friend, today: NaiveDate) -> Result<()> {
+        let due = friend.days_until_due(today)?;
+        if self.options.includes(&friend, &due) {
+            let priority = Priority {
+                sort: self.options.sort,
+                urgency: Urgency::new(&due, friend.frequency),
+                due,
+            };
+            self.queue.push(friend, priority);
+        }
+        Ok(())
+    }
+
+    pub fn into_records(
+        self,
+        today: NaiveDate,
+        display: &DueDisplay,
+    ) -> Result<Vec<UpcomingRecord>> {
+        let limit = self.options.limit.unwrap_or(usize::MAX);
+        self.queue
+            .into_sorted_iter()
+            .take(limit)
+            .map(|(friend, Priority { due, urgency, .. })| {
+                Ok(UpcomingRecord {
+                    record: FriendRecord::with_due(friend, due, today, display)?,
+                    urgency,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct UpcomingRecord {
+    #[serde(flatten)]
+    pub record: FriendRecord,
+    pub urgency: Urgency,
+}
+
+impl fmt::Display for UpcomingRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.record.fmt(f)
+    }
+}
+
+impl Record for UpcomingRecord {
+    fn table_titles() -> Row {
+        Self::table_titles_with(&DueDisplay::default())
+    }
+
+    fn table_titles_with(display: &DueDisplay) -> Row {
+        let mut r = Friend::get_table_titles(display);
+        r.add_cell(Cell::new("Urgency"));
+        r
+    }
+
+    fn table_row(&self) -> Row {
+        let mut r = self.record.friend.get_table_row(self.record.due_cells());
+        r.add_cell(Cell::new(&self.urgency.to_string()));
+        r
+    }
+
+    fn headers() -> Vec<&'static str> {
+        let mut headers = FriendRecord::headers();
+        headers.push("urgency");
+        headers
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = self.record.fields();
+        fields.push(output::optional_field(&self.urgency.score()));
+        fields
+    }
+}
+
+/// A friend's birthday or other important date within the days looked ahead.
+#[derive(Serialize)]
+pub struct UpcomingDateRecord {
+    #[serde(flatten)]
+    pub record: FriendRecord,
+    #[serde(flatten)]
+    pub date: DateRecord,
+}
+
+/// A row of `upcoming`: a friend coming due, or one of their important dates.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UpcomingRow {
+    Due(UpcomingRecord),
+    Date(UpcomingDateRecord),
+}
+
+impl fmt::Display for UpcomingRow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UpcomingRow::Due(record) => record.fmt(f),
+            UpcomingRow::Date(record) => {
+                write!(f, "{}: {}", record.record.friend.name, record.date)
             }
-        })
+        }
+    }
+}
+
+impl Record for UpcomingRow {
+    fn table_titles() -> Row {
+        UpcomingRecord::table_titles()
     }
 
-    pub fn print(&self) {
-        let mut table = Table::new();
-        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.set_titles(Friend::get_table_titles(true));
+    fn table_titles_with(display: &DueDisplay) -> Row {
+        UpcomingRecord::table_titles_with(display)
+    }
 
-        for (friend, due_days) in self.queue_by_due_days.clone().into_sorted_iter() {
-            let row = friend.get_table_row_with_due(due_days);
-            table.add_row(row);
+    fn table_row(&self) -> Row {
+        match self {
+            UpcomingRow::Due(record) => record.table_row(),
+            UpcomingRow::Date(record) => {
+                let date = &record.date;
+                let next = format!("{} {}", date.label, date.display.date(date.next));
+                let until = DueDays::DueIn(date.days_until)
+                    .display_some()
+                    .unwrap_or_default();
+                // The label goes with the date, or with how long until it when that's all
+                let cells = match date.display.style() {
+                    DueStyle::Relative => vec![format!("{} {}", date.label, until)],
+                    _ => date.display.cells(next, until),
+                };
+                let mut r = record.record.friend.get_table_row(cells);
+                r.add_cell(Cell::new(""));
+                r
+            }
         }
+    }
+
+    fn headers() -> Vec<&'static str> {
+        let mut headers = UpcomingRecord::headers();
+        headers.push("kind");
+        headers.extend(DateRecord::headers());
+        headers
+    }
 
-        table.printstd();
+    fn fields(&self) -> Vec<String> {
+        match self {
+            UpcomingRow::Due(record) => {
+                let mut fields = record.fields();
+                fields.push("due".to_string());
+                fields.extend(DateRecord::headers().iter().map(|_| String::new()));
+                fields
+            }
+            UpcomingRow::Date(record) => {
+                let mut fields = record.record.fields();
+                fields.push(String::new());
+                fields.push("date".to_string());
+                fields.extend(record.date.fields());
+                fields
+            }
+        }
     }
 }
 
@@ -93,7 +556,13 @@ impl UpcomingFriends {
 mod test {
     use priority_queue::PriorityQueue;
 
-    use super::DueDays;
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+
+    use super::{
+        Away, AwayPeriod, DueDays, DueDisplay, DueStyle, Frequency, Friend, ImportantDate,
+        Priority, UpcomingFriends, UpcomingOptions, UpcomingRow, UpcomingSort, Urgency,
+    };
 
     #[test]
     fn test_queue_order() {
@@ -136,6 +605,22 @@ mod test {
         assert_eq!(queue_by_due_days.pop(), None);
     }
 
+    #[test]
+    fn test_due_days_serialize() {
+        assert_eq!(
+            serde_json::to_string(&DueDays::NotSeen).unwrap(),
+            r#"{"status":"not_seen"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&DueDays::OverDue(5)).unwrap(),
+            r#"{"status":"overdue","days":5}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&DueDays::DueIn(3)).unwrap(),
+            r#"{"status":"due_in","days":3}"#
+        );
+    }
+
     #[test]
     fn test_display_due_on() {
         assert_eq!(DueDays::NotSeen.display_some(), None);
@@ -156,4 +641,200 @@ mod test {
             Some("in 24 days".to_owned())
         );
     }
+
+    fn priority(sort: UpcomingSort, due: DueDays, weeks: i32) -> Priority {
+        Priority {
+            sort,
+            urgency: Urgency::new(&due, Frequency::weeks(weeks)),
+            due,
+        }
+    }
+
+    #[test]
+    fn test_urgency_score() {
+        assert_eq!(
+            Urgency::new(&DueDays::NotSeen, Frequency::weeks(1)).score(),
+            None
+        );
+        assert_eq!(
+            Urgency::new(&DueDays::OverDue(7), Frequency::weeks(2)).score(),
+            Some(0.5)
+        );
+        assert_eq!(
+            Urgency::new(&DueDays::OverDue(0), Frequency::weeks(2)).score(),
+            Some(0.0)
+        );
+        assert_eq!(
+            Urgency::new(&DueDays::DueIn(7), Frequency::weeks(1)).score(),
+            Some(-1.0)
+        );
+        assert_eq!(
+            Urgency::new(&DueDays::OverDue(5), Frequency::weeks(1)).to_string(),
+            "0.71"
+        );
+        assert_eq!(Urgency::NotSeen.to_string(), "");
+    }
+
+    #[test]
+    fn test_queue_order_by_urgency() {
+        let mut queue: PriorityQueue<&str, Priority> = PriorityQueue::new();
+        let sort = UpcomingSort::Urgency;
+
+        queue.push("yearly", priority(sort, DueDays::OverDue(10), 52));
+        queue.push("weekly", priority(sort, DueDays::OverDue(5), 1));
+        queue.push("unseen", priority(sort, DueDays::NotSeen, 52));
+        queue.push("monthly soon", priority(sort, DueDays::DueIn(2), 4));
+        queue.push("weekly soon", priority(sort, DueDays::DueIn(2), 1));
+
+        let order: Vec<&str> = queue.into_sorted_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            order,
+            vec!["unseen", "weekly", "yearly", "monthly soon", "weekly soon"]
+        );
+    }
+
+    #[test]
+    fn test_queue_order_absolute() {
+        let mut queue: PriorityQueue<&str, Priority> = PriorityQueue::new();
+        let sort = UpcomingSort::Absolute;
+
+        queue.push("yearly", priority(sort, DueDays::OverDue(10), 52));
+        queue.push("weekly", priority(sort, DueDays::OverDue(5), 1));
+        queue.push("unseen", priority(sort, DueDays::NotSeen, 52));
+
+        let order: Vec<&str> = queue.into_sorted_iter().map(|(name, _)| name).collect();
+        assert_eq!(order, vec!["unseen", "yearly", "weekly"]);
+
+        // Equal when sorted the same, whatever the urgency
+        assert_eq!(
+            priority(sort, DueDays::OverDue(5), 1),
+            priority(sort, DueDays::OverDue(5), 52)
+        );
+    }
+
+    #[test]
+    fn test_options_includes() {
+        let friend = Friend::named(1, "Sam");
+
+        let default = UpcomingOptions::default();
+        assert!(default.includes(&friend, &DueDays::NotSeen));
+        assert!(default.includes(&friend, &DueDays::OverDue(30)));
+        assert!(default.includes(&friend, &DueDays::DueIn(10)));
+        assert!(!default.includes(&friend, &DueDays::DueIn(11)));
+
+        let options = UpcomingOptions {
+            days: 30,
+            ..Default::default()
+        };
+        assert!(options.includes(&friend, &DueDays::DueIn(30)));
+
+        let options = UpcomingOptions {
+            overdue_only: true,
+            include_unseen: false,
+            ..Default::default()
+        };
+        assert!(options.includes(&friend, &DueDays::OverDue(1)));
+        assert!(!options.includes(&friend, &DueDays::DueIn(0)));
+        assert!(!options.includes(&friend, &DueDays::NotSeen));
+
+        let options = UpcomingOptions {
+            location: Some("shire".to_string()),
+            ..Default::default()
+        };
+        assert!(options.includes(&friend, &DueDays::NotSeen));
+        let options = UpcomingOptions {
+            location: Some("Mordor".to_string()),
+            ..Default::default()
+        };
+        assert!(!options.includes(&friend, &DueDays::NotSeen));
+    }
+
+    #[test]
+    fn test_options_includes_away() {
+        let period = AwayPeriod {
+            id: 1,
+            location: "Bree".to_string(),
+            start_date: "2021-12-01".to_string(),
+            end_date: "2021-12-31".to_string(),
+        };
+        let today = NaiveDate::from_ymd_opt(2021, 12, 11).unwrap();
+        let options = UpcomingOptions {
+            away: Some(Away::new(&period, &BTreeMap::new(), today).unwrap()),
+            ..Default::default()
+        };
+
+        let home = Friend::named(1, "Sam");
+        assert!(!options.includes(&home, &DueDays::OverDue(30)));
+        assert!(!options.includes(&home, &DueDays::NotSeen));
+
+        let nearby = Friend::named(1, "Sam").located("Bree");
+        assert!(options.includes(&nearby, &DueDays::OverDue(30)));
+        assert!(options.includes(&nearby, &DueDays::DueIn(20)));
+        assert!(!options.includes(&nearby, &DueDays::DueIn(21)));
+
+        assert!(!options.includes_date(&home, 3));
+        assert!(options.includes_date(&nearby, 3));
+        assert!(!options.includes_date(&nearby, 11));
+    }
+
+    #[test]
+    fn test_rows_limit() {
+        let birthday = ImportantDate {
+            id: 1,
+            friend_id: 1,
+            label: "Birthday".to_string(),
+            year: None,
+            month: 12,
+            day: 14,
+        };
+        let today = NaiveDate::from_ymd_opt(2021, 12, 11).unwrap();
+        let mut upcoming = UpcomingFriends::new(UpcomingOptions {
+            limit: Some(2),
+            ..Default::default()
+        });
+        upcoming.push_date(&Friend::named(1, "Sam"), birthday, today);
+        upcoming.push(Friend::named(1, "Sam"), today).unwrap();
+        upcoming.push(Friend::named(2, "Rosie"), today).unwrap();
+
+        let rows = upcoming.into_rows(today, &DueDisplay::default()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(rows[0], UpcomingRow::Date(_)));
+        assert!(matches!(rows[1], UpcomingRow::Due(_)));
+    }
+
+    #[test]
+    fn test_due_display_describe() {
+        let due_date = NaiveDate::from_ymd_opt(2021, 12, 8);
+        let due = DueDays::DueIn(6);
+
+        let relative = DueDisplay::new(DueStyle::Relative, "%m/%d").unwrap();
+        assert_eq!(
+            relative.describe(&due, due_date),
+            Some("in 6 days".to_owned())
+        );
+        let absolute = DueDisplay::default();
+        assert_eq!(
+            absolute.describe(&due, due_date),
+            Some("on 12/08".to_owned())
+        );
+        let both = DueDisplay::new(DueStyle::Both, "%d.%m.%Y").unwrap();
+        assert_eq!(
+            both.describe(&due, due_date),
+            Some("on 08.12.2021 (in 6 days)".to_owned())
+        );
+        assert_eq!(both.describe(&DueDays::NotSeen, None), None);
+
+        assert_eq!(relative.titles(), vec!["Due"]);
+        assert_eq!(absolute.titles(), vec!["Due date"]);
+        assert_eq!(both.titles(), vec!["Due date", "Due"]);
+        let cells = |display: &DueDisplay| display.cells("12/08".into(), "in 6 days".into());
+        assert_eq!(cells(&relative), vec!["in 6 days"]);
+        assert_eq!(cells(&both), vec!["12/08", "in 6 days"]);
+    }
+
+    #[test]
+    fn test_due_display_invalid_format() {
+        assert!(DueDisplay::new(DueStyle::Absolute, "%Q").is_err());
+        assert!(DueDisplay::new(DueStyle::Absolute, "%Y-%m-%d").is_ok());
+    }
 }
@@ -0,0 +1,341 @@
+use crate::contacts::ContactKind;
+use crate::ics::{escape, fold};
+use crate::models::{Contact, Friend, ImportantDate};
+use anyhow::Result;
+
+const ADR_LOCALITY: usize = 3;
+
+#[derive(Debug, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    fn has_param(&self, key: &str, value: &str) -> bool {
+        self.params.iter().any(|(k, v)| {
+            k.eq_ignore_ascii_case(key) && v.split(',').any(|v| v.eq_ignore_ascii_case(value))
+        })
+    }
+
+    fn is_preferred(&self) -> bool {
+        self.has_param("TYPE", "pref") || self.params.iter().any(|(k, _)| k == "PREF")
+    }
+
+    fn components(&self) -> Vec<String> {
+        split_unescaped(&self.value, ';')
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Card {
+    pub properties: Vec<Property>,
+}
+
+impl Card {
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> + 'a {
+        self.properties.iter().filter(move |p| p.name == name)
+    }
+
+    pub fn formatted_name(&self) -> Option<String> {
+        let formatted = self
+            .all("FN")
+            .map(|p| unescape(&p.value).trim().to_string())
+            .find(|n| !n.is_empty());
+
+        // Fall back to the structured name, which is family;given;additional;prefix;suffix
+        formatted.or_else(|| {
+            let parts = self.all("N").next()?.components();
+            let given = parts.get(1).map(String::as_str).unwrap_or_default();
+            let family = parts.first().map(String::as_str).unwrap_or_default();
+            let name = format!("{} {}", given, family).trim().to_string();
+            Some(name).filter(|n| !n.is_empty())
+        })
+    }
+
+    pub fn locality(&self) -> Option<String> {
+        let mut addresses: Vec<&Property> = self.all("ADR").collect();
+        addresses.sort_by_key(|adr| !adr.is_preferred());
+        addresses
+            .into_iter()
+            .filter_map(|adr| adr.components().get(ADR_LOCALITY).cloned())
+            .map(|locality| locality.trim().to_string())
+            .find(|locality| !locality.is_empty())
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<Card>> {
+    let mut cards = vec![];
+    let mut current: Option<Card> = None;
+
+    for (line_number, line) in unfold(text).iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let property = parse_line(line)
+            .ok_or_else(|| anyhow::anyhow!("Invalid vCard line {}: '{}'", line_number + 1, line))?;
+
+        match (
+            property.name.as_str(),
+            property.value.to_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VCARD") => current = Some(Card::default()),
+            ("END", "VCARD") => {
+                let card = current
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("END:VCARD without BEGIN:VCARD"))?;
+                cards.push(card);
+            }
+            _ => match current.as_mut() {
+                Some(card) => card.properties.push(property),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Property {} is outside of a vCard",
+                        property.name
+                    ))
+                }
+            },
+        }
+    }
+
+    if current.is_some() {
+        return Err(anyhow::anyhow!("Missing END:VCARD at end of file"));
+    }
+    Ok(cards)
+}
+
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    let (head, value) = line.split_once(':')?;
+    let mut head_parts = head.split(';');
+
+    // Grouped properties look like item1.ADR
+    let name = head_parts.next()?;
+    let name = name.rsplit('.').next()?.trim().to_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    let params = head_parts
+        .map(|param| match param.split_once('=') {
+            Some((key, value)) => (key.to_uppercase(), value.trim_matches('"').to_string()),
+            // vCard 2.1 style parameters like ADR;HOME
+            None => ("TYPE".to_string(), param.to_string()),
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                part.push(c);
+                if let Some(escaped) = chars.next() {
+                    part.push(escaped);
+                }
+            }
+            c if c == separator => parts.push(unescape(&std::mem::take(&mut part))),
+            c => part.push(c),
+        }
+    }
+    parts.push(unescape(&part));
+    parts
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// A friend as a vCard 3.0, with their contact details and birthday when the year is known.
+pub fn card(friend: &Friend, contacts: &[Contact], birthday: Option<&ImportantDate>) -> String {
+    // The structured name is family;given;additional;prefix;suffix
+    let (given, family) = match friend.name.trim().rsplit_once(' ') {
+        Some((given, family)) => (given, family),
+        None => (friend.name.as_str(), ""),
+    };
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:3.0".to_string(),
+        format!(
+            "PRODID:-//friendgrow//friendgrow {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        format!("FN:{}", escape(&friend.name)),
+        format!("N:{};{};;;", escape(family), escape(given)),
+    ];
+    if !friend.location.is_empty() {
+        lines.push(format!("ADR:;;;{};;;", escape(&friend.location)));
+    }
+    for contact in contacts {
+        let name = match contact.kind {
+            ContactKind::Phone => "TEL",
+            ContactKind::Email => "EMAIL",
+            ContactKind::Handle => "X-SOCIALPROFILE",
+        };
+        let label = contact.label.replace('"', "");
+        match label.as_str() {
+            "" => lines.push(format!("{}:{}", name, escape(&contact.value))),
+            label => lines.push(format!(
+                "{};TYPE=\"{}\":{}",
+                name,
+                label,
+                escape(&contact.value)
+            )),
+        }
+    }
+    if let Some(ImportantDate {
+        year: Some(year),
+        month,
+        day,
+        ..
+    }) = birthday
+    {
+        lines.push(format!("BDAY:{:04}-{:02}-{:02}", year, month, day));
+    }
+    if !friend.tags.is_empty() {
+        let tags: Vec<String> = friend.tags.iter().map(|t| escape(t)).collect();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    lines.push("END:VCARD".to_string());
+    lines.iter().map(|l| fold(l) + "\r\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARDS: &str = "BEGIN:VCARD\r
+VERSION:3.0\r
+FN:Samwise Gamgee\r
+N:Gamgee;Samwise;;;\r
+ADR;TYPE=work:;;1 Bag End;Hobbiton;;;The Shire\r
+ADR;TYPE=home,pref:;;Gamgee Cottage;Bywater;;;The\r
+  Shire\r
+END:VCARD\r
+BEGIN:VCARD\r
+VERSION:4.0\r
+N:Baggins;Frodo;;;\r
+item1.ADR:;;Bag End\\, Bagshot Row;Hobbiton;;;\r
+END:VCARD\r
+";
+
+    #[test]
+    fn test_parse_cards() {
+        let cards = parse(CARDS).unwrap();
+        assert_eq!(cards.len(), 2);
+
+        assert_eq!(cards[0].formatted_name().unwrap(), "Samwise Gamgee");
+        assert_eq!(cards[0].locality().unwrap(), "Bywater");
+
+        assert_eq!(cards[1].formatted_name().unwrap(), "Frodo Baggins");
+        assert_eq!(cards[1].locality().unwrap(), "Hobbiton");
+    }
+
+    #[test]
+    fn test_unfold() {
+        let unfolded = unfold("NOTE:one\r\n two\r\n\tthree\r\nFN:x");
+        assert_eq!(unfolded, vec!["NOTE:onetwothree", "FN:x"]);
+    }
+
+    #[test]
+    fn test_split_unescaped() {
+        assert_eq!(
+            split_unescaped(";;Bag End\\, Bagshot Row;Hobbiton", ';'),
+            vec!["", "", "Bag End, Bagshot Row", "Hobbiton"]
+        );
+        assert_eq!(unescape("a\\nb\\;c"), "a\nb;c");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("FN:Nobody\r\n").is_err());
+        assert!(parse("BEGIN:VCARD\r\nFN:Nobody\r\n").is_err());
+        assert!(parse("BEGIN:VCARD\r\nnot a property\r\nEND:VCARD\r\n").is_err());
+    }
+
+    #[test]
+    fn test_missing_name_and_address() {
+        let cards = parse("BEGIN:VCARD\r\nVERSION:3.0\r\nEND:VCARD\r\n").unwrap();
+        assert_eq!(cards[0].formatted_name(), None);
+        assert_eq!(cards[0].locality(), None);
+    }
+
+    #[test]
+    fn test_card_round_trip() {
+        let friend = Friend::named(1, "Samwise Gamgee")
+            .located("Hobbiton")
+            .every(crate::frequency::Frequency::weeks(3))
+            .tagged("hobbits");
+        let contacts = vec![
+            Contact {
+                id: 1,
+                friend_id: 1,
+                kind: ContactKind::Phone,
+                label: "mobile".to_string(),
+                value: "+44 7700 900123".to_string(),
+            },
+            Contact {
+                id: 2,
+                friend_id: 1,
+                kind: ContactKind::Email,
+                label: String::new(),
+                value: "sam@bagend.me".to_string(),
+            },
+        ];
+        let birthday = ImportantDate {
+            id: 1,
+            friend_id: 1,
+            label: "Birthday".to_string(),
+            year: Some(1990),
+            month: 4,
+            day: 6,
+        };
+
+        let text = card(&friend, &contacts, Some(&birthday));
+        assert!(text.contains("N:Gamgee;Samwise;;;\r\n"));
+        assert!(text.contains("TEL;TYPE=\"mobile\":+44 7700 900123\r\n"));
+        assert!(text.contains("EMAIL:sam@bagend.me\r\n"));
+        assert!(text.contains("BDAY:1990-04-06\r\n"));
+
+        let cards = parse(&text).unwrap();
+        assert_eq!(cards[0].formatted_name().unwrap(), "Samwise Gamgee");
+        assert_eq!(cards[0].locality().unwrap(), "Hobbiton");
+    }
+}